@@ -1,12 +1,26 @@
 #![no_std]
 use soroban_sdk::{
-    contract, contractimpl, contracttype, symbol_short, vec, Address, Env, String, Vec,
-    contracterror,
+    contract, contractclient, contractimpl, contracttype, symbol_short, vec, Address, BytesN, Env, Map, String, Symbol,
+    TryFromVal, Val, Vec, contracterror,
 };
 
+mod ttl;
+
 #[derive(Clone, Debug, PartialEq)]
 #[contracttype]
 pub struct PatientData {
+    pub patient_id: Address,
+    pub exercise_routines: Vec<String>,
+    pub meal_plans: Vec<String>,
+    pub progress_updates: Vec<String>,
+    pub last_updated: u64,
+}
+
+// Patient data as stored before care teams, naming the patient's one trainer; only read by
+// `patient_data`, which converts it
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct LegacyPatientData {
     pub patient_id: Address,
     pub trainer_id: Address,
     pub exercise_routines: Vec<String>,
@@ -15,6 +29,15 @@ pub struct PatientData {
     pub last_updated: u64,
 }
 
+// What a provider on a patient's care team may update
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum CareRole {
+    Exercise,   // Exercise routines and progress
+    Nutrition,  // Meal plans and progress
+    Observer,   // Nothing; read only
+}
+
 #[derive(Clone, Debug, PartialEq)]
 #[contracttype]
 pub struct TrainerInfo {
@@ -23,13 +46,40 @@ pub struct TrainerInfo {
     pub patient_count: u32,
 }
 
+// Mirrors `Entitlements` in subscription-service; max_patients of 0 means no limit
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct Entitlements {
+    pub plan_id: u32,
+    pub max_patients: u32,
+    pub features: u32,
+}
+
+#[contractclient(name = "SubscriptionClient")]
+pub trait SubscriptionInterface {
+    fn get_entitlements(env: Env, user: Address) -> Option<Entitlements>;
+}
+
 #[derive(Clone)]
 #[contracttype]
 pub enum DataKey {
     TrainerInfo(Address),
     PatientData(Address),
-    TrainerPatientLink(Address, Address),
-    PatientTrainerLink(Address),
+    TrainerPatientLink(Address, Address),  // The trainer's CareRole for the patient
+    PatientTrainerLink(Address),           // The patient's care team, each trainer's CareRole
+    SubscriptionContract,
+    Admin,
+    PendingAdmin,
+    Paused,
+    TtlPolicy,
+}
+
+// How long storage entries are kept live, in ledgers
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct TtlPolicy {
+    pub threshold: u32,  // Entries with fewer ledgers left than this are extended on access
+    pub extend_to: u32,  // Ledgers an extended entry is kept live for
 }
 
 #[contracterror]
@@ -44,6 +94,13 @@ pub enum ContractError {
     NoLink = 6,
     WrongTrainer = 7,
     NotLinked = 8,
+    NoSubscription = 9,
+    PatientLimit = 10,
+    Paused = 11,
+    NoAdmin = 12,
+    NoPendingAdmin = 13,
+    InvalidTtl = 14,
+    RoleNotPermitted = 15,
 }
 
 #[contract]
@@ -51,14 +108,95 @@ pub struct TrainerPatientContract;
 
 #[contractimpl]
 impl TrainerPatientContract {
-    pub fn initialize(env: Env) {
+    // With a subscription contract, trainers can only link as many patients as their plan allows.
+    // The admin can pause trainer writes, hand over the role and upgrade the contract.
+    // This SDK has no constructor, so the first caller becomes the admin: deploy and initialize in
+    // one transaction, from a deployer contract that calls `env.deployer().deploy(wasm_hash)` and
+    // then `initialize` on the new address before returning.
+    pub fn initialize(env: Env, admin: Address, subscription_contract: Option<Address>) {
+        if env.storage().instance().has(&symbol_short!("init")) {
+            return;
+        }
         env.storage().instance().set(&symbol_short!("init"), &true);
+        env.storage().instance().set(&DataKey::Admin, &admin);
+
+        if let Some(subscription_contract) = subscription_contract {
+            env.storage().instance().set(&DataKey::SubscriptionContract, &subscription_contract);
+        }
+    }
+
+    // Offer the admin role to new_admin; the current admin keeps it until new_admin accepts
+    pub fn transfer_admin(env: Env, new_admin: Address) -> Result<(), ContractError> {
+        Self::require_admin(&env)?;
+        env.storage().instance().set(&DataKey::PendingAdmin, &new_admin);
+        Ok(())
+    }
+
+    pub fn accept_admin(env: Env) -> Result<(), ContractError> {
+        let new_admin: Address = env
+            .storage()
+            .instance()
+            .get(&DataKey::PendingAdmin)
+            .ok_or(ContractError::NoPendingAdmin)?;
+        new_admin.require_auth();
+
+        env.storage().instance().set(&DataKey::Admin, &new_admin);
+        env.storage().instance().remove(&DataKey::PendingAdmin);
+        Ok(())
+    }
+
+    pub fn get_admin(env: Env) -> Option<Address> {
+        env.storage().instance().get(&DataKey::Admin)
+    }
+
+    // While paused, trainer writes fail with Paused; reads and admin calls keep working
+    pub fn set_paused(env: Env, paused: bool) -> Result<(), ContractError> {
+        Self::require_admin(&env)?;
+        env.storage().instance().set(&DataKey::Paused, &paused);
+        Ok(())
+    }
+
+    pub fn is_paused(env: Env) -> bool {
+        env.storage().instance().get(&DataKey::Paused).unwrap_or(false)
+    }
+
+    // Replace the contract code with an uploaded WASM, keeping storage
+    pub fn upgrade(env: Env, new_wasm_hash: BytesN<32>) -> Result<(), ContractError> {
+        Self::require_admin(&env)?;
+        env.deployer().update_current_contract_wasm(new_wasm_hash);
+        Ok(())
+    }
+
+    // Entries with fewer than `threshold` ledgers left are extended to `extend_to` whenever
+    // they are read or written
+    pub fn set_ttl_policy(env: Env, threshold: u32, extend_to: u32) -> Result<(), ContractError> {
+        Self::require_admin(&env)?;
+        let policy = TtlPolicy { threshold, extend_to };
+        if !ttl::is_valid(&env, &policy) {
+            return Err(ContractError::InvalidTtl);
+        }
+        env.storage().instance().set(&DataKey::TtlPolicy, &policy);
+        ttl::extend_instance(&env);
+        Ok(())
+    }
+
+    pub fn get_ttl_policy(env: Env) -> TtlPolicy {
+        ttl::policy(&env)
+    }
+
+    // Anyone can keep entries nobody has touched lately from being archived
+    pub fn bump(env: Env, keys: Vec<DataKey>) {
+        ttl::extend_instance(&env);
+        for key in keys.iter() {
+            ttl::extend(&env, &key);
+        }
     }
 
     pub fn register_trainer(env: Env, trainer_id: Address) -> Result<(), ContractError> {
+        Self::require_not_paused(&env)?;
         trainer_id.require_auth();
 
-        if env.storage().persistent().has(&DataKey::TrainerInfo(trainer_id.clone())) {
+        if ttl::has(&env, &DataKey::TrainerInfo(trainer_id.clone())) {
             return Err(ContractError::TrainerExists);
         }
 
@@ -68,56 +206,58 @@ impl TrainerPatientContract {
             patient_count: 0,
         };
 
-        env.storage().persistent().set(&DataKey::TrainerInfo(trainer_id.clone()), &trainer_info);
-        env.storage().persistent().extend_ttl(&DataKey::TrainerInfo(trainer_id.clone()), 518400, 518400);
+        ttl::set(&env, &DataKey::TrainerInfo(trainer_id.clone()), &trainer_info);
 
         env.events().publish((symbol_short!("trainer"), symbol_short!("register")), trainer_id);
         Ok(())
     }
 
-    pub fn link_patient(env: Env, trainer_id: Address, patient_id: Address) -> Result<(), ContractError> {
+    // Add the trainer to the patient's care team in `role`. A patient can have several
+    // providers, but each trainer holds one role per patient.
+    pub fn link_patient(env: Env, trainer_id: Address, patient_id: Address, role: CareRole) -> Result<(), ContractError> {
+        Self::require_not_paused(&env)?;
         trainer_id.require_auth();
 
-        let mut trainer_info: TrainerInfo = env
-            .storage()
-            .persistent()
-            .get(&DataKey::TrainerInfo(trainer_id.clone()))
+        let mut trainer_info: TrainerInfo = ttl::get(&env, &DataKey::TrainerInfo(trainer_id.clone()))
             .ok_or(ContractError::NoTrainer)?;
 
         if !trainer_info.active {
             return Err(ContractError::Inactive);
         }
 
-        if env.storage().persistent().has(&DataKey::PatientTrainerLink(patient_id.clone())) {
+        if ttl::has(&env, &DataKey::TrainerPatientLink(trainer_id.clone(), patient_id.clone())) {
             return Err(ContractError::Linked);
         }
 
-        env.storage().persistent().set(
-            &DataKey::PatientTrainerLink(patient_id.clone()),
-            &trainer_id,
-        );
-        env.storage().persistent().extend_ttl(&DataKey::PatientTrainerLink(patient_id.clone()), 518400, 518400);
+        Self::verify_patient_limit(&env, &trainer_id, trainer_info.patient_count)?;
+
+        let mut team = match Self::care_team(&env, &patient_id) {
+            Some(team) => team,
+            None => {
+                // The first provider on a team starts the patient's records afresh
+                let patient_data = PatientData {
+                    patient_id: patient_id.clone(),
+                    exercise_routines: vec![&env],
+                    meal_plans: vec![&env],
+                    progress_updates: vec![&env],
+                    last_updated: env.ledger().timestamp(),
+                };
+                ttl::set(&env, &DataKey::PatientData(patient_id.clone()), &patient_data);
+                Map::new(&env)
+            }
+        };
+        team.set(trainer_id.clone(), role);
+
+        ttl::set(&env, &DataKey::PatientTrainerLink(patient_id.clone()), &team);
 
-        env.storage().persistent().set(
+        ttl::set(
+            &env,
             &DataKey::TrainerPatientLink(trainer_id.clone(), patient_id.clone()),
-            &true,
+            &role,
         );
-        env.storage().persistent().extend_ttl(&DataKey::TrainerPatientLink(trainer_id.clone(), patient_id.clone()), 518400, 518400);
-
-        let patient_data = PatientData {
-            patient_id: patient_id.clone(),
-            trainer_id: trainer_id.clone(),
-            exercise_routines: vec![&env],
-            meal_plans: vec![&env],
-            progress_updates: vec![&env],
-            last_updated: env.ledger().timestamp(),
-        };
-
-        env.storage().persistent().set(&DataKey::PatientData(patient_id.clone()), &patient_data);
-        env.storage().persistent().extend_ttl(&DataKey::PatientData(patient_id.clone()), 518400, 518400);
 
         trainer_info.patient_count += 1;
-        env.storage().persistent().set(&DataKey::TrainerInfo(trainer_id.clone()), &trainer_info);
+        ttl::set(&env, &DataKey::TrainerInfo(trainer_id.clone()), &trainer_info);
 
         env.events().publish(
             (symbol_short!("link"), symbol_short!("created")),
@@ -132,20 +272,16 @@ impl TrainerPatientContract {
         patient_id: Address,
         routines: Vec<String>,
     ) -> Result<(), ContractError> {
+        Self::require_not_paused(&env)?;
         trainer_id.require_auth();
-        Self::verify_trainer_patient_link(&env, &trainer_id, &patient_id)?;
+        Self::verify_role(&env, &trainer_id, &patient_id, CareRole::can_update_routines)?;
 
-        let mut patient_data: PatientData = env
-            .storage()
-            .persistent()
-            .get(&DataKey::PatientData(patient_id.clone()))
-            .ok_or(ContractError::NoData)?;
+        let mut patient_data = Self::patient_data(&env, &patient_id).ok_or(ContractError::NoData)?;
 
         patient_data.exercise_routines = routines;
         patient_data.last_updated = env.ledger().timestamp();
 
-        env.storage().persistent().set(&DataKey::PatientData(patient_id.clone()), &patient_data);
-        env.storage().persistent().extend_ttl(&DataKey::PatientData(patient_id.clone()), 518400, 518400);
+        ttl::set(&env, &DataKey::PatientData(patient_id.clone()), &patient_data);
 
         env.events().publish(
             (symbol_short!("update"), symbol_short!("exercise")),
@@ -160,20 +296,16 @@ impl TrainerPatientContract {
         patient_id: Address,
         meal_plans: Vec<String>,
     ) -> Result<(), ContractError> {
+        Self::require_not_paused(&env)?;
         trainer_id.require_auth();
-        Self::verify_trainer_patient_link(&env, &trainer_id, &patient_id)?;
+        Self::verify_role(&env, &trainer_id, &patient_id, CareRole::can_update_meal_plans)?;
 
-        let mut patient_data: PatientData = env
-            .storage()
-            .persistent()
-            .get(&DataKey::PatientData(patient_id.clone()))
-            .ok_or(ContractError::NoData)?;
+        let mut patient_data = Self::patient_data(&env, &patient_id).ok_or(ContractError::NoData)?;
 
         patient_data.meal_plans = meal_plans;
         patient_data.last_updated = env.ledger().timestamp();
 
-        env.storage().persistent().set(&DataKey::PatientData(patient_id.clone()), &patient_data);
-        env.storage().persistent().extend_ttl(&DataKey::PatientData(patient_id.clone()), 518400, 518400);
+        ttl::set(&env, &DataKey::PatientData(patient_id.clone()), &patient_data);
 
         env.events().publish(
             (symbol_short!("update"), symbol_short!("meal")),
@@ -188,20 +320,16 @@ impl TrainerPatientContract {
         patient_id: Address,
         progress_updates: Vec<String>,
     ) -> Result<(), ContractError> {
+        Self::require_not_paused(&env)?;
         trainer_id.require_auth();
-        Self::verify_trainer_patient_link(&env, &trainer_id, &patient_id)?;
+        Self::verify_role(&env, &trainer_id, &patient_id, CareRole::can_update_progress)?;
 
-        let mut patient_data: PatientData = env
-            .storage()
-            .persistent()
-            .get(&DataKey::PatientData(patient_id.clone()))
-            .ok_or(ContractError::NoData)?;
+        let mut patient_data = Self::patient_data(&env, &patient_id).ok_or(ContractError::NoData)?;
 
         patient_data.progress_updates = progress_updates;
         patient_data.last_updated = env.ledger().timestamp();
 
-        env.storage().persistent().set(&DataKey::PatientData(patient_id.clone()), &patient_data);
-        env.storage().persistent().extend_ttl(&DataKey::PatientData(patient_id.clone()), 518400, 518400);
+        ttl::set(&env, &DataKey::PatientData(patient_id.clone()), &patient_data);
 
         env.events().publish(
             (symbol_short!("update"), symbol_short!("progress")),
@@ -213,45 +341,43 @@ impl TrainerPatientContract {
     pub fn get_patient_data(env: Env, patient_id: Address) -> Result<PatientData, ContractError> {
         patient_id.require_auth();
 
-        env.storage()
-            .persistent()
-            .get(&DataKey::PatientData(patient_id))
-            .ok_or(ContractError::NoData)
+        Self::patient_data(&env, &patient_id).ok_or(ContractError::NoData)
     }
 
     pub fn get_trainer_info(env: Env, trainer_id: Address) -> Result<TrainerInfo, ContractError> {
-        env.storage()
-            .persistent()
-            .get(&DataKey::TrainerInfo(trainer_id))
+        ttl::get(&env, &DataKey::TrainerInfo(trainer_id))
             .ok_or(ContractError::NoTrainer)
     }
 
-    pub fn get_patient_trainer(env: Env, patient_id: Address) -> Result<Address, ContractError> {
+    // The providers on the patient's care team and their roles
+    pub fn get_care_team(env: Env, patient_id: Address) -> Result<Map<Address, CareRole>, ContractError> {
         patient_id.require_auth();
 
-        env.storage()
-            .persistent()
-            .get(&DataKey::PatientTrainerLink(patient_id))
-            .ok_or(ContractError::NoLink)
+        Self::care_team(&env, &patient_id).ok_or(ContractError::NoLink)
     }
 
+    // Take the trainer off the patient's care team; the rest of the team keeps access
     pub fn unlink_patient(env: Env, trainer_id: Address, patient_id: Address) -> Result<(), ContractError> {
+        Self::require_not_paused(&env)?;
         trainer_id.require_auth();
         Self::verify_trainer_patient_link(&env, &trainer_id, &patient_id)?;
 
-        env.storage().persistent().remove(&DataKey::PatientTrainerLink(patient_id.clone()));
+        let mut team = Self::care_team(&env, &patient_id).ok_or(ContractError::NoLink)?;
+        team.remove(trainer_id.clone());
+        if team.is_empty() {
+            env.storage().persistent().remove(&DataKey::PatientTrainerLink(patient_id.clone()));
+        } else {
+            ttl::set(&env, &DataKey::PatientTrainerLink(patient_id.clone()), &team);
+        }
         env.storage().persistent().remove(&DataKey::TrainerPatientLink(trainer_id.clone(), patient_id.clone()));
 
-        let mut trainer_info: TrainerInfo = env
-            .storage()
-            .persistent()
-            .get(&DataKey::TrainerInfo(trainer_id.clone()))
+        let mut trainer_info: TrainerInfo = ttl::get(&env, &DataKey::TrainerInfo(trainer_id.clone()))
             .ok_or(ContractError::NoTrainer)?;
 
         if trainer_info.patient_count > 0 {
             trainer_info.patient_count -= 1;
         }
-        env.storage().persistent().set(&DataKey::TrainerInfo(trainer_id.clone()), &trainer_info);
+        ttl::set(&env, &DataKey::TrainerInfo(trainer_id.clone()), &trainer_info);
 
         env.events().publish(
             (symbol_short!("link"), symbol_short!("removed")),
@@ -260,34 +386,117 @@ impl TrainerPatientContract {
         Ok(())
     }
 
+    fn require_admin(env: &Env) -> Result<(), ContractError> {
+        let admin: Address = env
+            .storage()
+            .instance()
+            .get(&DataKey::Admin)
+            .ok_or(ContractError::NoAdmin)?;
+        admin.require_auth();
+        Ok(())
+    }
+
+    fn require_not_paused(env: &Env) -> Result<(), ContractError> {
+        if Self::is_paused(env.clone()) {
+            return Err(ContractError::Paused);
+        }
+        Ok(())
+    }
+
+    fn verify_patient_limit(env: &Env, trainer_id: &Address, patient_count: u32) -> Result<(), ContractError> {
+        let subscription_contract: Option<Address> =
+            env.storage().instance().get(&DataKey::SubscriptionContract);
+        let Some(subscription_contract) = subscription_contract else {
+            return Ok(());
+        };
+
+        let entitlements = SubscriptionClient::new(env, &subscription_contract)
+            .get_entitlements(trainer_id)
+            .ok_or(ContractError::NoSubscription)?;
+
+        if entitlements.max_patients != 0 && patient_count >= entitlements.max_patients {
+            return Err(ContractError::PatientLimit);
+        }
+
+        Ok(())
+    }
+
+    // Returns the trainer's role on the patient's care team
     fn verify_trainer_patient_link(
         env: &Env,
         trainer_id: &Address,
         patient_id: &Address,
-    ) -> Result<(), ContractError> {
-        let linked_trainer: Address = env
-            .storage()
-            .persistent()
-            .get(&DataKey::PatientTrainerLink(patient_id.clone()))
-            .ok_or(ContractError::NoLink)?;
+    ) -> Result<CareRole, ContractError> {
+        let team = Self::care_team(env, patient_id).ok_or(ContractError::NoLink)?;
 
-        if linked_trainer != *trainer_id {
+        if !team.contains_key(trainer_id.clone()) {
             return Err(ContractError::WrongTrainer);
         }
 
-        let is_linked: bool = env
-            .storage()
-            .persistent()
-            .get(&DataKey::TrainerPatientLink(trainer_id.clone(), patient_id.clone()))
-            .unwrap_or(false);
+        let link: Val = ttl::get(env, &DataKey::TrainerPatientLink(trainer_id.clone(), patient_id.clone()))
+            .ok_or(ContractError::NotLinked)?;
+        // Links made before care teams were stored as `true`
+        if bool::try_from_val(env, &link).is_ok() {
+            return Ok(CareRole::Exercise);
+        }
+        CareRole::try_from_val(env, &link).map_err(|_| ContractError::NotLinked)
+    }
+
+    // Records and links stored before care teams are read in the current layout, and written
+    // back in it by the next change. A patient's one trainer becomes their Exercise provider.
+    fn care_team(env: &Env, patient_id: &Address) -> Option<Map<Address, CareRole>> {
+        let team: Val = ttl::get(env, &DataKey::PatientTrainerLink(patient_id.clone()))?;
+        // Before care teams this held the patient's one trainer
+        if let Ok(trainer_id) = Address::try_from_val(env, &team) {
+            return Some(Map::from_array(env, [(trainer_id, CareRole::Exercise)]));
+        }
+        Map::try_from_val(env, &team).ok()
+    }
 
-        if !is_linked {
-            return Err(ContractError::NotLinked);
+    fn patient_data(env: &Env, patient_id: &Address) -> Option<PatientData> {
+        let stored: Val = ttl::get(env, &DataKey::PatientData(patient_id.clone()))?;
+        // Decoding a record into the wrong struct traps instead of failing, so check the layout first
+        let fields = Map::<Symbol, Val>::try_from_val(env, &stored).ok()?;
+        if !fields.contains_key(Symbol::new(env, "trainer_id")) {
+            return PatientData::try_from_val(env, &stored).ok();
         }
+        let legacy = LegacyPatientData::try_from_val(env, &stored).ok()?;
+        Some(PatientData {
+            patient_id: legacy.patient_id,
+            exercise_routines: legacy.exercise_routines,
+            meal_plans: legacy.meal_plans,
+            progress_updates: legacy.progress_updates,
+            last_updated: legacy.last_updated,
+        })
+    }
 
+    fn verify_role(
+        env: &Env,
+        trainer_id: &Address,
+        patient_id: &Address,
+        permits: fn(&CareRole) -> bool,
+    ) -> Result<(), ContractError> {
+        let role = Self::verify_trainer_patient_link(env, trainer_id, patient_id)?;
+        if !permits(&role) {
+            return Err(ContractError::RoleNotPermitted);
+        }
         Ok(())
     }
 }
 
+impl CareRole {
+    pub fn can_update_routines(&self) -> bool {
+        *self == CareRole::Exercise
+    }
+
+    pub fn can_update_meal_plans(&self) -> bool {
+        *self == CareRole::Nutrition
+    }
+
+    pub fn can_update_progress(&self) -> bool {
+        *self != CareRole::Observer
+    }
+}
+
 #[cfg(test)]
 mod test;
@@ -1,7 +1,7 @@
 #![cfg(test)]
 
 use super::*;
-use soroban_sdk::{testutils::Address as _, vec, Address, Env, String};
+use soroban_sdk::{testutils::Address as _, vec, Address, BytesN, Env, String};
 
 extern crate std;
 
@@ -14,12 +14,12 @@ fn test_trainer_registration() {
     let trainer = Address::generate(&env);
     env.mock_all_auths();
 
-    client.initialize();
+    client.initialize(&Address::generate(&env), &None);
     client.register_trainer(&trainer);
 
     let trainer_info = client.get_trainer_info(&trainer);
     assert_eq!(trainer_info.trainer_id, trainer);
-    assert_eq!(trainer_info.active, true);
+    assert!(trainer_info.active);
     assert_eq!(trainer_info.patient_count, 0);
 }
 
@@ -33,12 +33,12 @@ fn test_link_patient() {
     let patient = Address::generate(&env);
     env.mock_all_auths();
 
-    client.initialize();
+    client.initialize(&Address::generate(&env), &None);
     client.register_trainer(&trainer);
-    client.link_patient(&trainer, &patient);
+    client.link_patient(&trainer, &patient, &CareRole::Exercise);
 
-    let linked_trainer = client.get_patient_trainer(&patient);
-    assert_eq!(linked_trainer, trainer);
+    let team = client.get_care_team(&patient);
+    assert_eq!(team.get(trainer.clone()), Some(CareRole::Exercise));
 
     let trainer_info = client.get_trainer_info(&trainer);
     assert_eq!(trainer_info.patient_count, 1);
@@ -51,12 +51,15 @@ fn test_update_patient_data() {
     let client = TrainerPatientContractClient::new(&env, &contract_id);
 
     let trainer = Address::generate(&env);
+    let nutritionist = Address::generate(&env);
     let patient = Address::generate(&env);
     env.mock_all_auths();
 
-    client.initialize();
+    client.initialize(&Address::generate(&env), &None);
     client.register_trainer(&trainer);
-    client.link_patient(&trainer, &patient);
+    client.register_trainer(&nutritionist);
+    client.link_patient(&trainer, &patient, &CareRole::Exercise);
+    client.link_patient(&nutritionist, &patient, &CareRole::Nutrition);
 
     let routines = vec![
         &env,
@@ -77,7 +80,7 @@ fn test_update_patient_data() {
     ];
 
     client.update_exercise_routines(&trainer, &patient, &routines);
-    client.update_meal_plans(&trainer, &patient, &meals);
+    client.update_meal_plans(&nutritionist, &patient, &meals);
     client.update_progress(&trainer, &patient, &progress);
 
     let patient_data = client.get_patient_data(&patient);
@@ -97,10 +100,10 @@ fn test_unauthorized_update() {
     let patient = Address::generate(&env);
     env.mock_all_auths();
 
-    client.initialize();
+    client.initialize(&Address::generate(&env), &None);
     client.register_trainer(&trainer1);
     client.register_trainer(&trainer2);
-    client.link_patient(&trainer1, &patient);
+    client.link_patient(&trainer1, &patient, &CareRole::Exercise);
 
     let routines = vec![&env, String::from_str(&env, "Unauthorized")];
     let result = client.try_update_exercise_routines(&trainer2, &patient, &routines);
@@ -121,9 +124,9 @@ fn test_unlink_patient() {
     let patient = Address::generate(&env);
     env.mock_all_auths();
 
-    client.initialize();
+    client.initialize(&Address::generate(&env), &None);
     client.register_trainer(&trainer);
-    client.link_patient(&trainer, &patient);
+    client.link_patient(&trainer, &patient, &CareRole::Exercise);
 
     let trainer_info = client.get_trainer_info(&trainer);
     assert_eq!(trainer_info.patient_count, 1);
@@ -143,7 +146,7 @@ fn test_duplicate_trainer_registration() {
     let trainer = Address::generate(&env);
     env.mock_all_auths();
 
-    client.initialize();
+    client.initialize(&Address::generate(&env), &None);
     client.register_trainer(&trainer);
 
     let result = client.try_register_trainer(&trainer);
@@ -165,17 +168,17 @@ fn test_patient_already_linked() {
     let patient = Address::generate(&env);
     env.mock_all_auths();
 
-    client.initialize();
+    client.initialize(&Address::generate(&env), &None);
     client.register_trainer(&trainer1);
     client.register_trainer(&trainer2);
-    client.link_patient(&trainer1, &patient);
+    client.link_patient(&trainer1, &patient, &CareRole::Exercise);
 
-    let result = client.try_link_patient(&trainer2, &patient);
-    assert!(result.is_err());
+    let result = client.try_link_patient(&trainer1, &patient, &CareRole::Nutrition);
+    assert_eq!(result, Err(Ok(ContractError::Linked)), "A trainer holds one role per patient");
 
-    if let Err(Ok(error)) = result {
-        assert_eq!(error, ContractError::Linked);
-    }
+    // Another provider can join the patient's care team
+    client.link_patient(&trainer2, &patient, &CareRole::Nutrition);
+    assert_eq!(client.get_care_team(&patient).len(), 2);
 }
 
 #[test]
@@ -187,7 +190,7 @@ fn test_get_nonexistent_data() {
     let patient = Address::generate(&env);
     env.mock_all_auths();
 
-    client.initialize();
+    client.initialize(&Address::generate(&env), &None);
 
     let result = client.try_get_patient_data(&patient);
     assert!(result.is_err());
@@ -206,7 +209,7 @@ fn test_get_nonexistent_trainer() {
     let trainer = Address::generate(&env);
     env.mock_all_auths();
 
-    client.initialize();
+    client.initialize(&Address::generate(&env), &None);
 
     let result = client.try_get_trainer_info(&trainer);
     assert!(result.is_err());
@@ -226,9 +229,9 @@ fn test_patient_access_own_data() {
     let patient = Address::generate(&env);
     env.mock_all_auths();
 
-    client.initialize();
+    client.initialize(&Address::generate(&env), &None);
     client.register_trainer(&trainer);
-    client.link_patient(&trainer, &patient);
+    client.link_patient(&trainer, &patient, &CareRole::Exercise);
 
     let routines = vec![
         &env,
@@ -240,7 +243,7 @@ fn test_patient_access_own_data() {
 
     let patient_data = client.get_patient_data(&patient);
     assert_eq!(patient_data.patient_id, patient);
-    assert_eq!(patient_data.trainer_id, trainer);
+    assert_eq!(client.get_care_team(&patient).get(trainer), Some(CareRole::Exercise));
     assert_eq!(patient_data.exercise_routines, routines);
 }
 
@@ -255,16 +258,350 @@ fn test_multiple_patients_per_trainer() {
     let patient2 = Address::generate(&env);
     env.mock_all_auths();
 
-    client.initialize();
+    client.initialize(&Address::generate(&env), &None);
     client.register_trainer(&trainer);
-    client.link_patient(&trainer, &patient1);
-    client.link_patient(&trainer, &patient2);
+    client.link_patient(&trainer, &patient1, &CareRole::Exercise);
+    client.link_patient(&trainer, &patient2, &CareRole::Exercise);
 
     let trainer_info = client.get_trainer_info(&trainer);
     assert_eq!(trainer_info.patient_count, 2);
 
-    let linked_trainer1 = client.get_patient_trainer(&patient1);
-    let linked_trainer2 = client.get_patient_trainer(&patient2);
-    assert_eq!(linked_trainer1, trainer);
-    assert_eq!(linked_trainer2, trainer);
+    assert!(client.get_care_team(&patient1).contains_key(trainer.clone()));
+    assert!(client.get_care_team(&patient2).contains_key(trainer.clone()));
+}
+
+// Stands in for subscription-service, returning whatever entitlements a test sets
+#[contract]
+pub struct MockSubscription;
+
+#[contractimpl]
+impl MockSubscription {
+    pub fn set_entitlements(env: Env, user: Address, entitlements: Entitlements) {
+        env.storage().persistent().set(&user, &entitlements);
+    }
+
+    pub fn get_entitlements(env: Env, user: Address) -> Option<Entitlements> {
+        env.storage().persistent().get(&user)
+    }
+}
+
+fn setup_with_subscription(env: &Env) -> (TrainerPatientContractClient<'_>, MockSubscriptionClient<'_>) {
+    let subscription_id = env.register_contract(None, MockSubscription);
+    let contract_id = env.register_contract(None, TrainerPatientContract);
+    let client = TrainerPatientContractClient::new(env, &contract_id);
+    env.mock_all_auths();
+
+    client.initialize(&Address::generate(env), &Some(subscription_id.clone()));
+    (client, MockSubscriptionClient::new(env, &subscription_id))
+}
+
+#[test]
+fn test_link_patient_plan_limit() {
+    let env = Env::default();
+    let (client, subscription) = setup_with_subscription(&env);
+
+    let trainer = Address::generate(&env);
+    subscription.set_entitlements(&trainer, &Entitlements { plan_id: 1, max_patients: 2, features: 0 });
+    client.register_trainer(&trainer);
+
+    let first = Address::generate(&env);
+    client.link_patient(&trainer, &first, &CareRole::Exercise);
+    client.link_patient(&trainer, &Address::generate(&env), &CareRole::Exercise);
+
+    let patient = Address::generate(&env);
+    let result = client.try_link_patient(&trainer, &patient, &CareRole::Exercise);
+    assert_eq!(result, Err(Ok(ContractError::PatientLimit)));
+    assert_eq!(client.get_trainer_info(&trainer).patient_count, 2);
+
+    // Unlinking frees a slot
+    client.unlink_patient(&trainer, &first);
+    client.link_patient(&trainer, &patient, &CareRole::Exercise);
+    assert_eq!(client.get_trainer_info(&trainer).patient_count, 2);
+}
+
+#[test]
+fn test_link_patient_unlimited_plan() {
+    let env = Env::default();
+    let (client, subscription) = setup_with_subscription(&env);
+    client.initialize(&Address::generate(&env), &None); // Already initialized, so the subscription contract is kept
+
+    let trainer = Address::generate(&env);
+    subscription.set_entitlements(&trainer, &Entitlements { plan_id: 3, max_patients: 0, features: 0 });
+    client.register_trainer(&trainer);
+
+    for _ in 0..5 {
+        client.link_patient(&trainer, &Address::generate(&env), &CareRole::Exercise);
+    }
+    assert_eq!(client.get_trainer_info(&trainer).patient_count, 5);
+}
+
+#[test]
+fn test_link_patient_without_subscription() {
+    let env = Env::default();
+    let (client, _subscription) = setup_with_subscription(&env);
+
+    let trainer = Address::generate(&env);
+    client.register_trainer(&trainer);
+
+    let result = client.try_link_patient(&trainer, &Address::generate(&env), &CareRole::Exercise);
+    assert_eq!(result, Err(Ok(ContractError::NoSubscription)));
+}
+
+#[test]
+fn test_admin_handover() {
+    let env = Env::default();
+    let contract_id = env.register_contract(None, TrainerPatientContract);
+    let client = TrainerPatientContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let new_admin = Address::generate(&env);
+    env.mock_all_auths();
+
+    client.initialize(&admin, &None);
+    client.initialize(&new_admin, &None); // Already initialized, so the admin is kept
+    assert_eq!(client.get_admin(), Some(admin.clone()));
+    assert_eq!(client.try_accept_admin(), Err(Ok(ContractError::NoPendingAdmin)));
+
+    // The old admin keeps the role until the new one accepts
+    client.transfer_admin(&new_admin);
+    assert_eq!(env.auths()[0].0, admin);
+    assert_eq!(client.get_admin(), Some(admin.clone()));
+
+    client.accept_admin();
+    assert_eq!(env.auths()[0].0, new_admin);
+    assert_eq!(client.get_admin(), Some(new_admin.clone()));
+
+    client.set_paused(&true);
+    assert_eq!(env.auths()[0].0, new_admin);
+}
+
+#[test]
+fn test_admin_calls_require_admin() {
+    let env = Env::default();
+    let contract_id = env.register_contract(None, TrainerPatientContract);
+    let client = TrainerPatientContractClient::new(&env, &contract_id);
+    env.mock_all_auths();
+
+    assert_eq!(client.try_set_paused(&true), Err(Ok(ContractError::NoAdmin)));
+    client.initialize(&Address::generate(&env), &None);
+
+    env.set_auths(&[]);
+    assert!(client.try_set_paused(&true).is_err());
+    assert!(client.try_transfer_admin(&Address::generate(&env)).is_err());
+    assert!(client.try_upgrade(&BytesN::from_array(&env, &[0; 32])).is_err());
+    assert!(!client.is_paused());
+}
+
+#[test]
+fn test_pause_blocks_trainer_writes() {
+    let env = Env::default();
+    let contract_id = env.register_contract(None, TrainerPatientContract);
+    let client = TrainerPatientContractClient::new(&env, &contract_id);
+
+    let trainer = Address::generate(&env);
+    let patient = Address::generate(&env);
+    env.mock_all_auths();
+
+    client.initialize(&Address::generate(&env), &None);
+    client.register_trainer(&trainer);
+    client.link_patient(&trainer, &patient, &CareRole::Exercise);
+
+    client.set_paused(&true);
+    assert!(client.is_paused());
+    assert_eq!(client.try_register_trainer(&Address::generate(&env)), Err(Ok(ContractError::Paused)));
+    assert_eq!(client.try_link_patient(&trainer, &Address::generate(&env), &CareRole::Exercise), Err(Ok(ContractError::Paused)));
+    let routines = vec![&env, String::from_str(&env, "Rest day")];
+    assert_eq!(client.try_update_exercise_routines(&trainer, &patient, &routines), Err(Ok(ContractError::Paused)));
+    assert_eq!(client.try_unlink_patient(&trainer, &patient), Err(Ok(ContractError::Paused)));
+
+    // Reads keep working
+    assert!(client.get_care_team(&patient).contains_key(trainer.clone()));
+
+    client.set_paused(&false);
+    client.update_exercise_routines(&trainer, &patient, &routines);
+}
+
+// Close `days` worth of ledgers
+fn advance_days(env: &Env, days: u32) {
+    use soroban_sdk::testutils::Ledger;
+    env.ledger().with_mut(|ledger| {
+        ledger.sequence_number += days * ttl::DAY_IN_LEDGERS;
+        ledger.timestamp += days as u64 * 86_400;
+    });
+}
+
+fn persistent_ttl(env: &Env, contract_id: &Address, key: &DataKey) -> u32 {
+    use soroban_sdk::testutils::storage::Persistent;
+    env.as_contract(contract_id, || env.storage().persistent().get_ttl(key))
+}
+
+#[test]
+fn test_patient_data_stays_live_while_used() {
+    let env = Env::default();
+    let contract_id = env.register_contract(None, TrainerPatientContract);
+    let client = TrainerPatientContractClient::new(&env, &contract_id);
+
+    let trainer = Address::generate(&env);
+    let patient = Address::generate(&env);
+    env.mock_all_auths();
+
+    client.initialize(&Address::generate(&env), &None);
+    client.register_trainer(&trainer);
+    client.link_patient(&trainer, &patient, &CareRole::Exercise);
+    let key = DataKey::PatientData(patient.clone());
+    assert_eq!(persistent_ttl(&env, &contract_id, &key), ttl::DEFAULT_EXTEND_TO);
+
+    // Every trainer write and patient read past the threshold extends the entries it touches
+    let routines = vec![&env, String::from_str(&env, "Rest day")];
+    for _ in 0..3 {
+        advance_days(&env, 20);
+        client.update_exercise_routines(&trainer, &patient, &routines);
+        assert_eq!(client.get_patient_data(&patient).exercise_routines, routines);
+        assert_eq!(persistent_ttl(&env, &contract_id, &key), ttl::DEFAULT_EXTEND_TO);
+    }
+}
+
+#[test]
+fn test_ttl_policy_and_bump() {
+    let env = Env::default();
+    let contract_id = env.register_contract(None, TrainerPatientContract);
+    let client = TrainerPatientContractClient::new(&env, &contract_id);
+    env.mock_all_auths();
+
+    client.initialize(&Address::generate(&env), &None);
+    assert_eq!(client.try_set_ttl_policy(&1_000, &1_000), Err(Ok(ContractError::InvalidTtl)));
+    assert_eq!(client.try_set_ttl_policy(&0, &1_000), Err(Ok(ContractError::InvalidTtl)));
+    client.set_ttl_policy(&(2 * ttl::DAY_IN_LEDGERS), &(10 * ttl::DAY_IN_LEDGERS));
+    let policy = client.get_ttl_policy();
+    assert_eq!(policy.extend_to, 10 * ttl::DAY_IN_LEDGERS);
+
+    let trainer = Address::generate(&env);
+    client.register_trainer(&trainer);
+    let key = DataKey::TrainerInfo(trainer.clone());
+    assert_eq!(persistent_ttl(&env, &contract_id, &key), policy.extend_to);
+
+    // Nobody reads the trainer, but anyone can keep it live
+    advance_days(&env, 9);
+    env.set_auths(&[]);
+    client.bump(&vec![&env, key.clone(), DataKey::PatientData(Address::generate(&env))]);
+    assert!(env.auths().is_empty());
+    assert_eq!(persistent_ttl(&env, &contract_id, &key), policy.extend_to);
+
+    advance_days(&env, 9);
+    assert!(client.get_trainer_info(&trainer).active);
+}
+
+#[test]
+fn test_care_roles_scope_updates() {
+    let env = Env::default();
+    let contract_id = env.register_contract(None, TrainerPatientContract);
+    let client = TrainerPatientContractClient::new(&env, &contract_id);
+
+    let coach = Address::generate(&env);
+    let dietitian = Address::generate(&env);
+    let observer = Address::generate(&env);
+    let patient = Address::generate(&env);
+    env.mock_all_auths();
+
+    client.initialize(&Address::generate(&env), &None);
+    for trainer in [&coach, &dietitian, &observer] {
+        client.register_trainer(trainer);
+    }
+    client.link_patient(&coach, &patient, &CareRole::Exercise);
+    client.link_patient(&dietitian, &patient, &CareRole::Nutrition);
+    client.link_patient(&observer, &patient, &CareRole::Observer);
+
+    let notes = vec![&env, String::from_str(&env, "Week 1")];
+    assert!(client.try_update_exercise_routines(&coach, &patient, &notes).is_ok());
+    assert_eq!(client.try_update_meal_plans(&coach, &patient, &notes), Err(Ok(ContractError::RoleNotPermitted)));
+    assert!(client.try_update_meal_plans(&dietitian, &patient, &notes).is_ok());
+    assert_eq!(client.try_update_exercise_routines(&dietitian, &patient, &notes), Err(Ok(ContractError::RoleNotPermitted)));
+    assert!(client.try_update_progress(&coach, &patient, &notes).is_ok());
+    assert!(client.try_update_progress(&dietitian, &patient, &notes).is_ok());
+    assert_eq!(client.try_update_progress(&observer, &patient, &notes), Err(Ok(ContractError::RoleNotPermitted)));
+}
+
+#[test]
+fn test_unlink_keeps_rest_of_care_team() {
+    let env = Env::default();
+    let contract_id = env.register_contract(None, TrainerPatientContract);
+    let client = TrainerPatientContractClient::new(&env, &contract_id);
+
+    let coach = Address::generate(&env);
+    let dietitian = Address::generate(&env);
+    let patient = Address::generate(&env);
+    env.mock_all_auths();
+
+    client.initialize(&Address::generate(&env), &None);
+    client.register_trainer(&coach);
+    client.register_trainer(&dietitian);
+    client.link_patient(&coach, &patient, &CareRole::Exercise);
+    client.link_patient(&dietitian, &patient, &CareRole::Nutrition);
+    let meals = vec![&env, String::from_str(&env, "Breakfast: Oatmeal")];
+    client.update_meal_plans(&dietitian, &patient, &meals);
+
+    client.unlink_patient(&coach, &patient);
+    let team = client.get_care_team(&patient);
+    assert_eq!(team.len(), 1);
+    assert_eq!(team.get(dietitian.clone()), Some(CareRole::Nutrition));
+    let routines = vec![&env, String::from_str(&env, "Rest day")];
+    assert_eq!(client.try_update_exercise_routines(&coach, &patient, &routines), Err(Ok(ContractError::WrongTrainer)));
+
+    // Rejoining a team that still exists keeps the patient's records
+    client.link_patient(&coach, &patient, &CareRole::Exercise);
+    assert_eq!(client.get_patient_data(&patient).meal_plans, meals);
+
+    client.unlink_patient(&coach, &patient);
+    client.unlink_patient(&dietitian, &patient);
+    assert_eq!(client.try_get_care_team(&patient), Err(Ok(ContractError::NoLink)));
+}
+
+#[test]
+fn test_records_stored_before_care_teams() {
+    let env = Env::default();
+    let contract_id = env.register_contract(None, TrainerPatientContract);
+    let client = TrainerPatientContractClient::new(&env, &contract_id);
+
+    let trainer = Address::generate(&env);
+    let dietitian = Address::generate(&env);
+    let patient = Address::generate(&env);
+    env.mock_all_auths();
+
+    client.initialize(&Address::generate(&env), &None);
+    client.register_trainer(&trainer);
+    client.register_trainer(&dietitian);
+
+    // Write the link as the single-trainer release did
+    let meals = vec![&env, String::from_str(&env, "Breakfast: Oatmeal")];
+    env.as_contract(&contract_id, || {
+        let storage = env.storage().persistent();
+        storage.set(&DataKey::PatientTrainerLink(patient.clone()), &trainer);
+        storage.set(&DataKey::TrainerPatientLink(trainer.clone(), patient.clone()), &true);
+        storage.set(&DataKey::PatientData(patient.clone()), &LegacyPatientData {
+            patient_id: patient.clone(),
+            trainer_id: trainer.clone(),
+            exercise_routines: vec![&env],
+            meal_plans: meals.clone(),
+            progress_updates: vec![&env],
+            last_updated: 0,
+        });
+    });
+
+    // The trainer is read back as the team's exercise provider, with the patient's records
+    assert_eq!(client.get_care_team(&patient), Map::from_array(&env, [(trainer.clone(), CareRole::Exercise)]));
+    assert_eq!(client.get_patient_data(&patient).meal_plans, meals);
+    let routines = vec![&env, String::from_str(&env, "Knee rehab")];
+    client.update_exercise_routines(&trainer, &patient, &routines);
+    assert_eq!(client.get_patient_data(&patient).exercise_routines, routines);
+
+    // Joining the team stores it in the current layout
+    client.link_patient(&dietitian, &patient, &CareRole::Nutrition);
+    env.as_contract(&contract_id, || {
+        let team: Map<Address, CareRole> = env.storage().persistent().get(&DataKey::PatientTrainerLink(patient.clone())).unwrap();
+        assert_eq!(team.len(), 2);
+        assert_eq!(team.get(trainer.clone()), Some(CareRole::Exercise));
+    });
+
+    client.unlink_patient(&trainer, &patient);
+    assert_eq!(client.get_care_team(&patient), Map::from_array(&env, [(dietitian.clone(), CareRole::Nutrition)]));
 }
@@ -0,0 +1,55 @@
+use soroban_sdk::{Env, IntoVal, TryFromVal, Val};
+use crate::{DataKey, TtlPolicy};
+
+// The TTL policy of the sisyphus contracts, documented in sisyphus/smart_contracts/libs/storage-ttl.
+// Copied rather than shared because this crate builds against an older soroban-sdk.
+pub const DAY_IN_LEDGERS: u32 = 17_280;
+pub const DEFAULT_THRESHOLD: u32 = 15 * DAY_IN_LEDGERS;
+pub const DEFAULT_EXTEND_TO: u32 = 30 * DAY_IN_LEDGERS;
+
+pub fn policy(env: &Env) -> TtlPolicy {
+    env.storage().instance().get(&DataKey::TtlPolicy).unwrap_or(TtlPolicy {
+        threshold: DEFAULT_THRESHOLD,
+        extend_to: DEFAULT_EXTEND_TO,
+    })
+}
+
+pub fn is_valid(env: &Env, policy: &TtlPolicy) -> bool {
+    policy.threshold > 0 && policy.threshold < policy.extend_to && policy.extend_to <= env.storage().max_ttl()
+}
+
+// The instance holds the admin and subscription contract
+pub fn extend_instance(env: &Env) {
+    let policy = policy(env);
+    env.storage().instance().extend_ttl(policy.threshold, policy.extend_to);
+}
+
+pub fn extend(env: &Env, key: &DataKey) -> bool {
+    let persistent = env.storage().persistent();
+    if !persistent.has(key) {
+        return false;
+    }
+    let policy = policy(env);
+    persistent.extend_ttl(key, policy.threshold, policy.extend_to);
+    true
+}
+
+pub fn has(env: &Env, key: &DataKey) -> bool {
+    extend_instance(env);
+    extend(env, key)
+}
+
+pub fn get<V: TryFromVal<Env, Val>>(env: &Env, key: &DataKey) -> Option<V> {
+    extend_instance(env);
+    let value = env.storage().persistent().get(key);
+    if value.is_some() {
+        extend(env, key);
+    }
+    value
+}
+
+pub fn set<V: IntoVal<Env, Val>>(env: &Env, key: &DataKey, value: &V) {
+    extend_instance(env);
+    env.storage().persistent().set(key, value);
+    extend(env, key);
+}
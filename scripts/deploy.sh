@@ -29,7 +29,8 @@ soroban contract invoke \
     --source alice \
     --network testnet \
     -- \
-    initialize
+    initialize \
+    --admin "$(soroban keys address alice)"
 
 echo "Contract initialized successfully!"
 echo "Contract ID: $CONTRACT_ID" > ../../contract_id.txt
@@ -4,6 +4,7 @@ set -e
 
 CONTRACT_ID=$(cat contract_id.txt)
 TRAINER_KEY="GDQERTYUIOPASDFGHJKLZXCVBNM1234567890QWERTYUIOPASDFGH"
+NUTRITIONIST_KEY="GCZXCVBNMASDFGHJKLQWERTYUIOP0987654321ZXCVBNMASDFGHJKL"
 PATIENT_KEY="GBASDFGHJKLZXCVBNM1234567890QWERTYUIOPASDFGHJKLZXCVBNM"
 
 echo "Registering trainer..."
@@ -15,7 +16,16 @@ soroban contract invoke \
     register_trainer \
     --trainer_id $TRAINER_KEY
 
-echo "Linking patient to trainer..."
+echo "Registering nutritionist..."
+soroban contract invoke \
+    --id $CONTRACT_ID \
+    --source nutritionist \
+    --network testnet \
+    -- \
+    register_trainer \
+    --trainer_id $NUTRITIONIST_KEY
+
+echo "Linking patient to trainer and nutritionist..."
 soroban contract invoke \
     --id $CONTRACT_ID \
     --source trainer \
@@ -23,7 +33,18 @@ soroban contract invoke \
     -- \
     link_patient \
     --trainer_id $TRAINER_KEY \
-    --patient_id $PATIENT_KEY
+    --patient_id $PATIENT_KEY \
+    --role Exercise
+
+soroban contract invoke \
+    --id $CONTRACT_ID \
+    --source nutritionist \
+    --network testnet \
+    -- \
+    link_patient \
+    --trainer_id $NUTRITIONIST_KEY \
+    --patient_id $PATIENT_KEY \
+    --role Nutrition
 
 echo "Updating exercise routines..."
 soroban contract invoke \
@@ -39,11 +60,11 @@ soroban contract invoke \
 echo "Updating meal plans..."
 soroban contract invoke \
     --id $CONTRACT_ID \
-    --source trainer \
+    --source nutritionist \
     --network testnet \
     -- \
     update_meal_plans \
-    --trainer_id $TRAINER_KEY \
+    --trainer_id $NUTRITIONIST_KEY \
     --patient_id $PATIENT_KEY \
     --meal_plans '["Breakfast: Oatmeal", "Lunch: Salad", "Dinner: Grilled fish"]'
 
@@ -0,0 +1,29 @@
+use soroban_sdk::{Address, Env};
+use crate::types::{Config, DataKey};
+use crate::errors::Error;
+use crate::payment;
+
+// Require the configured admin's authorization and return the configuration
+pub fn require_admin(env: &Env) -> Result<Config, Error> {
+    let config = payment::get_config(env)?;
+    config.admin.require_auth();
+    Ok(config)
+}
+
+// Whether the admin has stopped all non-admin mutations
+pub fn is_paused(env: &Env) -> bool {
+    env.storage().instance().get(&DataKey::Paused).unwrap_or(false)
+}
+
+// Reject the call while the contract is paused
+pub fn require_not_paused(env: &Env) -> Result<(), Error> {
+    if is_paused(env) {
+        return Err(Error::ContractPaused);
+    }
+    Ok(())
+}
+
+// Address the admin has offered the role to, if a handover is in progress
+pub fn pending_admin(env: &Env) -> Option<Address> {
+    env.storage().instance().get(&DataKey::PendingAdmin)
+}
@@ -6,19 +6,91 @@ use soroban_sdk::{contracterror, String};
 pub enum Error {
     InvalidInput = 1,
     ActiveSubscriptionExists = 2,
-    InvalidTransactionId = 3,
     SubscriptionNotFound = 4,
     SubscriptionStillActive = 5,
+    NotInitialized = 6,
+    InsufficientBalance = 7,
+    PlanNotFound = 8,
+    PlanInactive = 9,
+    UnsupportedAsset = 10,
+    InvalidStatusTransition = 11,
+    StatusUnchanged = 12,
+    UnknownLegacyStatus = 13,
+    RefundsDisabled = 14,
+    RefundUnavailable = 15,
+    SubscriptionExpired = 16,
+    InsufficientAllowance = 17,
+    PromoNotFound = 18,
+    PromoExpired = 19,
+    PromoExhausted = 20,
+    PromoNotApplicable = 21,
+    PromoAlreadyRedeemed = 22,
+    PromoExists = 23,
+    TrialUnavailable = 24,
+    TrialAlreadyUsed = 25,
+    PlanUnchanged = 26,
+    SubscriptionNotActive = 27,
+    PauseUnavailable = 28,
+    SubscriptionNotPaused = 29,
+    PriceUnavailable = 30,
+    PriceStale = 31,
+    SlippageExceeded = 32,
+    OrganizationExists = 33,
+    OrganizationNotFound = 34,
+    AlreadyMember = 35,
+    NotMember = 36,
+    SeatsExhausted = 37,
+    SeatsUnchanged = 38,
+    SeatsInUse = 39,
+    ContractPaused = 40,
+    NoPendingAdmin = 41,
+    SubscriptionPaused = 42,
 }
 
 impl Error {
     pub fn to_string(&self, env: &soroban_sdk::Env) -> String {
         match self {
-            Error::InvalidInput => String::from_str(env, "Invalid duration or payment amount"),
+            Error::InvalidInput => String::from_str(env, "Invalid input"),
             Error::ActiveSubscriptionExists => String::from_str(env, "Active subscription already exists"),
-            Error::InvalidTransactionId => String::from_str(env, "Invalid transaction ID"),
             Error::SubscriptionNotFound => String::from_str(env, "Subscription not found"),
             Error::SubscriptionStillActive => String::from_str(env, "Subscription still active"),
+            Error::NotInitialized => String::from_str(env, "Contract not initialized"),
+            Error::InsufficientBalance => String::from_str(env, "Insufficient token balance"),
+            Error::PlanNotFound => String::from_str(env, "Plan not found"),
+            Error::PlanInactive => String::from_str(env, "Plan is retired"),
+            Error::UnsupportedAsset => String::from_str(env, "Asset not accepted for payment"),
+            Error::InvalidStatusTransition => String::from_str(env, "Status transition not allowed"),
+            Error::StatusUnchanged => String::from_str(env, "Subscription already has this status"),
+            Error::UnknownLegacyStatus => String::from_str(env, "Stored status string is not recognized"),
+            Error::RefundsDisabled => String::from_str(env, "Refunds are not enabled"),
+            Error::RefundUnavailable => String::from_str(env, "Treasury cannot cover the refund"),
+            Error::SubscriptionExpired => String::from_str(env, "Subscription has already expired"),
+            Error::InsufficientAllowance => String::from_str(env, "Token allowance cannot cover the renewal"),
+            Error::PromoNotFound => String::from_str(env, "Promo code not found"),
+            Error::PromoExpired => String::from_str(env, "Promo code has expired"),
+            Error::PromoExhausted => String::from_str(env, "Promo code has no redemptions left"),
+            Error::PromoNotApplicable => String::from_str(env, "Promo code does not apply to this plan"),
+            Error::PromoAlreadyRedeemed => String::from_str(env, "Promo code already redeemed by this user"),
+            Error::PromoExists => String::from_str(env, "Promo code already exists"),
+            Error::TrialUnavailable => String::from_str(env, "Plan has no free trial"),
+            Error::TrialAlreadyUsed => String::from_str(env, "Free trial already used"),
+            Error::PlanUnchanged => String::from_str(env, "Subscription is already on this plan"),
+            Error::SubscriptionNotActive => String::from_str(env, "Subscription is not active"),
+            Error::PauseUnavailable => String::from_str(env, "Pausing is not enabled"),
+            Error::SubscriptionNotPaused => String::from_str(env, "Subscription is not paused"),
+            Error::PriceUnavailable => String::from_str(env, "No exchange rate available for this plan and asset"),
+            Error::PriceStale => String::from_str(env, "Exchange rate is older than the maximum age"),
+            Error::SlippageExceeded => String::from_str(env, "Converted price exceeds the maximum amount"),
+            Error::OrganizationExists => String::from_str(env, "Owner already has an organization"),
+            Error::OrganizationNotFound => String::from_str(env, "Organization not found"),
+            Error::AlreadyMember => String::from_str(env, "Address already holds a seat in an organization"),
+            Error::NotMember => String::from_str(env, "Address is not a member of this organization"),
+            Error::SeatsExhausted => String::from_str(env, "All seats are taken"),
+            Error::SeatsUnchanged => String::from_str(env, "Organization already has this many seats"),
+            Error::SeatsInUse => String::from_str(env, "More members than the requested seats"),
+            Error::ContractPaused => String::from_str(env, "Contract is paused"),
+            Error::NoPendingAdmin => String::from_str(env, "No admin handover in progress"),
+            Error::SubscriptionPaused => String::from_str(env, "Subscription is paused"),
         }
     }
 }
@@ -0,0 +1,59 @@
+use soroban_sdk::{symbol_short, Address, Env, IntoVal, Symbol, Val};
+use crate::types::{Subscription, SubscriptionEvent, SubscriptionStatus};
+
+// Version of the event layout, published as the third topic. Bump it whenever a topic or
+// payload changes shape so indexers can tell the layouts apart.
+// 2: `SubscriptionEvent` gained `asset`.
+pub const EVENT_VERSION: u32 = 2;
+
+// Every event is published under `("sub", name, EVENT_VERSION, user)`
+fn publish(env: &Env, name: Symbol, user: &Address, data: impl IntoVal<Env, Val>) {
+    env.events().publish((symbol_short!("sub"), name, EVENT_VERSION, user.clone()), data);
+}
+
+// The subscription's current period, with `amount` being what the event moved
+fn period(sub: &Subscription, amount: i128) -> SubscriptionEvent {
+    SubscriptionEvent {
+        plan_id: sub.plan_id,
+        amount,
+        asset: sub.asset.clone(),
+        period_start: sub.start_date,
+        period_end: sub.expires_at(),
+    }
+}
+
+// A subscription was bought, with a promo code or as a free trial
+pub fn created(env: &Env, sub: &Subscription) {
+    publish(env, symbol_short!("created"), &sub.user, period(sub, sub.payment_amount));
+}
+
+// A subscription was renewed, by the user or an auto-renewal, for a new period
+pub fn renewed(env: &Env, sub: &Subscription) {
+    publish(env, symbol_short!("renewed"), &sub.user, period(sub, sub.payment_amount));
+}
+
+// A subscription was upgraded and a new period started on the new plan; `amount` is the net
+// charge after crediting the unused period, negative if the credit was paid back
+pub fn plan_changed(env: &Env, sub: &Subscription, amount: i128) {
+    publish(env, symbol_short!("changed"), &sub.user, period(sub, amount));
+}
+
+// A subscription's period ended without a renewal; `amount` is what the period cost
+pub fn expired(env: &Env, sub: &Subscription) {
+    publish(env, symbol_short!("expired"), &sub.user, period(sub, sub.payment_amount));
+}
+
+// A subscription was cancelled; `amount` is the refund returned to the user (0 if none)
+pub fn cancelled(env: &Env, sub: &Subscription, refund: i128) {
+    publish(env, symbol_short!("cancelled"), &sub.user, period(sub, refund));
+}
+
+// A subscription moved from one status to another
+pub fn status_changed(env: &Env, user: &Address, from: SubscriptionStatus, to: SubscriptionStatus) {
+    publish(env, symbol_short!("status"), user, (from, to));
+}
+
+// An auto-renewal could not be charged and the subscription is now PastDue
+pub fn past_due(env: &Env, user: &Address) {
+    publish(env, symbol_short!("past_due"), user, ());
+}
@@ -0,0 +1,57 @@
+use soroban_sdk::{Address, Env, Vec};
+use crate::types::{DataKey, PaymentKind, PaymentPage, PaymentRecord, Subscription};
+use crate::errors::Error;
+use crate::ttl;
+use crate::index::MAX_PAGE_SIZE;
+
+// Number of payments recorded for `user`
+pub fn len(env: &Env, user: &Address) -> u32 {
+    ttl::persistent(env).get(&DataKey::PaymentCount(user.clone())).unwrap_or(0)
+}
+
+// Append a payment to `user`'s history. Records are never changed or removed.
+pub fn record(env: &Env, user: &Address, record: &PaymentRecord) {
+    let count = len(env, user);
+    let storage = ttl::persistent(env);
+    storage.set(&DataKey::Payment(user.clone(), count), record);
+    storage.set(&DataKey::PaymentCount(user.clone()), &(count + 1));
+}
+
+// Record the payment that started `sub`'s current period
+pub fn record_period(env: &Env, sub: &Subscription, kind: PaymentKind) {
+    record(
+        env,
+        &sub.user,
+        &PaymentRecord {
+            kind,
+            plan_id: sub.plan_id,
+            amount: sub.payment_amount,
+            asset: sub.asset.clone(),
+            period_start: sub.start_date,
+            period_end: sub.expires_at(),
+            timestamp: sub.payment_date,
+            ledger: sub.payment_ledger,
+        },
+    );
+}
+
+// Read `user`'s payments at positions `cursor..cursor + limit`, oldest first
+pub fn page(env: &Env, user: &Address, cursor: u32, limit: u32) -> Result<PaymentPage, Error> {
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(Error::InvalidInput);
+    }
+
+    let storage = ttl::persistent(env);
+    let count = len(env, user);
+    let end = cursor.saturating_add(limit).min(count);
+
+    let mut records = Vec::new(env);
+    for position in cursor..end {
+        records.push_back(storage.get(&DataKey::Payment(user.clone(), position)).unwrap());
+    }
+
+    Ok(PaymentPage {
+        records,
+        next_cursor: if end < count { Some(end) } else { None },
+    })
+}
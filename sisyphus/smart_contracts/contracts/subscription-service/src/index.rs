@@ -0,0 +1,57 @@
+use soroban_sdk::{Address, Env, Vec};
+use crate::types::{DataKey, Subscription, SubscriptionPage};
+use crate::errors::Error;
+use crate::ttl;
+
+// Largest page `list_subscriptions`/`list_active` will read in one call
+pub const MAX_PAGE_SIZE: u32 = 100;
+
+// Number of users ever indexed
+pub fn len(env: &Env) -> u32 {
+    ttl::persistent(env).get(&DataKey::SubscriberCount).unwrap_or(0)
+}
+
+// Append a user to the index, unless they are already in it
+pub fn push(env: &Env, user: &Address) {
+    let storage = ttl::persistent(env);
+    let marker = DataKey::Indexed(user.clone());
+    if storage.has(&marker) {
+        return;
+    }
+
+    let count = len(env);
+    storage.set(&DataKey::Subscriber(count), user);
+    storage.set(&marker, &true);
+    storage.set(&DataKey::SubscriberCount, &(count + 1));
+}
+
+// Read index positions `cursor..cursor + limit` and keep the subscriptions matching `filter`.
+// Cost depends on `limit`, never on the total number of subscribers.
+pub fn page(
+    env: &Env,
+    cursor: u32,
+    limit: u32,
+    filter: impl Fn(&Subscription) -> bool,
+) -> Result<SubscriptionPage, Error> {
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(Error::InvalidInput);
+    }
+
+    let storage = ttl::persistent(env);
+    let count = len(env);
+    let end = cursor.saturating_add(limit).min(count);
+
+    let mut subscriptions = Vec::new(env);
+    for position in cursor..end {
+        let user: Address = storage.get(&DataKey::Subscriber(position)).unwrap();
+        let sub: Subscription = storage.get(&DataKey::Subscriptions(user)).unwrap();
+        if filter(&sub) {
+            subscriptions.push_back(sub);
+        }
+    }
+
+    Ok(SubscriptionPage {
+        subscriptions,
+        next_cursor: if end < count { Some(end) } else { None },
+    })
+}
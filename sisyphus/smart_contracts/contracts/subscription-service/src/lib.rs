@@ -1,6 +1,17 @@
 pub mod subscription;
 pub mod types;
 pub mod errors;
+pub mod payment;
+pub mod admin;
+pub mod plan;
+pub mod status;
+pub mod index;
+pub mod events;
+pub mod promo;
+pub mod history;
+pub mod oracle;
+pub mod org;
+pub mod ttl;
 
 pub use subscription::*;
 
@@ -0,0 +1,63 @@
+use soroban_sdk::{contractclient, contracttype, token, Address, Env, Symbol};
+use crate::types::{DataKey, OracleConfig};
+use crate::errors::Error;
+use crate::ttl;
+
+// Asset identifier of the SEP-40 price feed interface
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Asset {
+    Stellar(Address),          // A token contract on this network
+    Other(Symbol),             // An off-chain asset such as a fiat currency
+}
+
+// A price reported by a SEP-40 feed
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PriceData {
+    pub price: i128,           // Price of one whole unit in the feed's base asset, scaled by `decimals`
+    pub timestamp: u64,        // When the price was recorded
+}
+
+// The part of the SEP-40 price feed interface this contract reads. The feed's base asset
+// must be USD.
+#[contractclient(name = "PriceOracleClient")]
+pub trait PriceOracle {
+    fn decimals(env: Env) -> u32;
+    fn lastprice(env: Env, asset: Asset) -> Option<PriceData>;
+}
+
+// The configured price feed, if any
+pub fn get(env: &Env) -> Option<OracleConfig> {
+    env.storage().instance().get(&DataKey::PriceOracle)
+}
+
+// Price per period of `plan_id` in US cents, if it has one
+pub fn usd_price(env: &Env, plan_id: u32) -> Option<i128> {
+    ttl::persistent(env).get(&DataKey::UsdPrice(plan_id))
+}
+
+// Convert `usd_cents` to base units of `asset` at the feed's latest price, rounding up so the
+// treasury is never short. Fails if there is no feed, it has no price for `asset`, or the
+// price is older than the configured maximum age.
+pub fn convert(env: &Env, usd_cents: i128, asset: &Address) -> Result<i128, Error> {
+    let config = get(env).ok_or(Error::PriceUnavailable)?;
+    let feed = PriceOracleClient::new(env, &config.address);
+    let data = feed
+        .lastprice(&Asset::Stellar(asset.clone()))
+        .ok_or(Error::PriceUnavailable)?;
+    if data.price <= 0 {
+        return Err(Error::PriceUnavailable);
+    }
+    if env.ledger().timestamp().saturating_sub(data.timestamp) > config.max_age {
+        return Err(Error::PriceStale);
+    }
+
+    // amount = cents / 100 * 10^token_decimals / (price / 10^feed_decimals)
+    let scale = 10i128
+        .checked_pow(token::Client::new(env, asset).decimals() + feed.decimals())
+        .ok_or(Error::PriceUnavailable)?;
+    let numerator = usd_cents.checked_mul(scale).ok_or(Error::PriceUnavailable)?;
+    let denominator = data.price.checked_mul(100).ok_or(Error::PriceUnavailable)?;
+    Ok(numerator / denominator + (numerator % denominator != 0) as i128)
+}
@@ -0,0 +1,56 @@
+use soroban_sdk::{Address, Env};
+use crate::types::{DataKey, Organization};
+use crate::errors::Error;
+use crate::ttl;
+
+pub fn get(env: &Env, owner: &Address) -> Result<Organization, Error> {
+    ttl::persistent(env)
+        .get(&DataKey::Organization(owner.clone()))
+        .ok_or(Error::OrganizationNotFound)
+}
+
+pub fn save(env: &Env, org: &Organization) {
+    ttl::persistent(env).set(&DataKey::Organization(org.owner.clone()), org);
+}
+
+// Owner of the organization `member` holds a seat in, if any
+pub fn owner_of(env: &Env, member: &Address) -> Option<Address> {
+    ttl::persistent(env).get(&DataKey::MemberOf(member.clone()))
+}
+
+// Give `member` a seat in `org`, which the caller saves
+pub fn add_member(env: &Env, org: &mut Organization, member: &Address) {
+    org.members.push_back(member.clone());
+    ttl::persistent(env).set(&DataKey::MemberOf(member.clone()), &org.owner);
+}
+
+// Seats the organization owned by `owner` is charged for in its current period; 1 for a user
+// without an organization
+pub fn seats(env: &Env, owner: &Address) -> u32 {
+    get(env, owner).map(|org| org.seats).unwrap_or(1)
+}
+
+// Seat count the owner has reduced to, applied when the next period starts
+pub fn pending_seats(env: &Env, owner: &Address) -> Option<u32> {
+    ttl::persistent(env).get(&DataKey::PendingSeats(owner.clone()))
+}
+
+// Seats the next period is charged for: a scheduled reduction, or else the current count
+pub fn next_seats(env: &Env, owner: &Address) -> u32 {
+    pending_seats(env, owner).unwrap_or_else(|| seats(env, owner))
+}
+
+// Apply a scheduled seat reduction as a new period starts
+pub fn apply_pending(env: &Env, owner: &Address) {
+    if let Some(seats) = pending_seats(env, owner) {
+        if let Ok(mut org) = get(env, owner) {
+            org.seats = seats;
+            save(env, &org);
+        }
+        clear_pending(env, owner);
+    }
+}
+
+pub fn clear_pending(env: &Env, owner: &Address) {
+    ttl::persistent(env).remove(&DataKey::PendingSeats(owner.clone()));
+}
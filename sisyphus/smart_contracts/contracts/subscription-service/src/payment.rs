@@ -0,0 +1,85 @@
+use soroban_sdk::{token, Address, Env};
+use crate::types::{Config, DataKey, Subscription};
+use crate::errors::Error;
+use crate::ttl;
+
+// Load the payment configuration set at deployment, extending the instance that holds it
+pub fn get_config(env: &Env) -> Result<Config, Error> {
+    ttl::extend_instance(env);
+    env.storage()
+        .instance()
+        .get(&DataKey::Config)
+        .ok_or(Error::NotInitialized)
+}
+
+// Whether `asset` is accepted for payment: the configured token or one the admin has added
+pub fn is_accepted(env: &Env, asset: &Address) -> Result<bool, Error> {
+    let config = get_config(env)?;
+    Ok(*asset == config.token || env.storage().instance().has(&DataKey::AcceptedAsset(asset.clone())))
+}
+
+// Pull `amount` of `asset` from `from` into the treasury.
+// Returns the ledger sequence the transfer settled in.
+pub fn collect(env: &Env, asset: &Address, from: &Address, amount: i128) -> Result<u32, Error> {
+    let config = get_config(env)?;
+    if !is_accepted(env, asset)? {
+        return Err(Error::UnsupportedAsset);
+    }
+    let client = token::Client::new(env, asset);
+
+    if client.balance(from) < amount {
+        return Err(Error::InsufficientBalance);
+    }
+
+    client.transfer(from, &config.treasury, &amount);
+    Ok(env.ledger().sequence())
+}
+
+// Pull `amount` of `asset` from `from` into the treasury using the allowance `from` granted
+// this contract, so no signature from `from` is needed. Returns the settlement ledger sequence.
+pub fn collect_approved(env: &Env, asset: &Address, from: &Address, amount: i128) -> Result<u32, Error> {
+    let config = get_config(env)?;
+    if !is_accepted(env, asset)? {
+        return Err(Error::UnsupportedAsset);
+    }
+    let client = token::Client::new(env, asset);
+    let spender = env.current_contract_address();
+
+    if client.allowance(from, &spender) < amount {
+        return Err(Error::InsufficientAllowance);
+    }
+    if client.balance(from) < amount {
+        return Err(Error::InsufficientBalance);
+    }
+
+    client.transfer_from(&spender, from, &config.treasury, &amount);
+    Ok(env.ledger().sequence())
+}
+
+// Whether the admin has allowed cancellations to refund unused days
+pub fn refunds_enabled(env: &Env) -> bool {
+    env.storage().instance().get(&DataKey::RefundsEnabled).unwrap_or(false)
+}
+
+// Share of the last payment covering the time left in the period at `now`, rounded down
+pub fn prorated_refund(sub: &Subscription, now: u64) -> i128 {
+    let period = sub.duration as u64 * 86_400;
+    let unused = sub.expires_at().saturating_sub(now).min(period);
+    sub.payment_amount * unused as i128 / period as i128
+}
+
+// Send `amount` of `asset` from the treasury back to `to`. The treasury funds refunds by
+// approving this contract as a spender of each payment token. Returns the ledger sequence
+// the transfer settled in.
+pub fn refund(env: &Env, to: &Address, asset: &Address, amount: i128) -> Result<u32, Error> {
+    let config = get_config(env)?;
+    let client = token::Client::new(env, asset);
+    let spender = env.current_contract_address();
+
+    if client.allowance(&config.treasury, &spender) < amount || client.balance(&config.treasury) < amount {
+        return Err(Error::RefundUnavailable);
+    }
+
+    client.transfer_from(&spender, &config.treasury, to, &amount);
+    Ok(env.ledger().sequence())
+}
@@ -0,0 +1,88 @@
+use soroban_sdk::{Address, Env};
+use crate::types::{DataKey, Plan, Subscription};
+use crate::errors::Error;
+use crate::ttl;
+use crate::oracle;
+
+// Load a plan from the catalog
+pub fn get(env: &Env, plan_id: u32) -> Result<Plan, Error> {
+    ttl::persistent(env)
+        .get(&DataKey::Plan(plan_id))
+        .ok_or(Error::PlanNotFound)
+}
+
+// Load a plan that can still be bought
+pub fn get_active(env: &Env, plan_id: u32) -> Result<Plan, Error> {
+    let plan = get(env, plan_id)?;
+    if !plan.active {
+        return Err(Error::PlanInactive);
+    }
+    Ok(plan)
+}
+
+pub fn save(env: &Env, plan: &Plan) {
+    ttl::persistent(env).set(&DataKey::Plan(plan.id), plan);
+}
+
+// Allocate the next plan ID; IDs start at 1
+pub fn next_id(env: &Env) -> u32 {
+    let count: u32 = ttl::persistent(env).get(&DataKey::PlanCount).unwrap_or(0);
+    ttl::persistent(env).set(&DataKey::PlanCount, &(count + 1));
+    count + 1
+}
+
+// Price per period of `plan` in `asset`
+pub fn price(plan: &Plan, asset: &Address) -> Result<i128, Error> {
+    plan.prices.get(asset.clone()).ok_or(Error::UnsupportedAsset)
+}
+
+// Price per period of `plan` in `asset` for a renewal: its fixed price in `asset`, or else
+// its US dollar price converted at the feed's latest rate
+pub fn renewal_price(env: &Env, plan: &Plan, asset: &Address) -> Result<i128, Error> {
+    match (plan.prices.get(asset.clone()), oracle::usd_price(env, plan.id)) {
+        (Some(price), _) => Ok(price),
+        (None, Some(usd_cents)) => oracle::convert(env, usd_cents, asset),
+        (None, None) => Err(Error::UnsupportedAsset),
+    }
+}
+
+// Whether moving from `current` to `new` is an upgrade: a higher price per day, given each
+// plan's price per period in the same asset
+pub fn is_upgrade(current: &Plan, current_price: i128, new: &Plan, new_price: i128) -> bool {
+    new_price * current.duration_days as i128 > current_price * new.duration_days as i128
+}
+
+// Plan a user has scheduled a downgrade to, if any
+pub fn pending(env: &Env, user: &Address) -> Option<u32> {
+    ttl::persistent(env).get(&DataKey::PendingPlan(user.clone()))
+}
+
+// Switch `user` to `plan_id` at their next renewal
+pub fn schedule(env: &Env, user: &Address, plan_id: u32) {
+    ttl::persistent(env).set(&DataKey::PendingPlan(user.clone()), &plan_id);
+}
+
+pub fn clear_pending(env: &Env, user: &Address) {
+    ttl::persistent(env).remove(&DataKey::PendingPlan(user.clone()));
+}
+
+// The plan `sub` renews under: a scheduled downgrade, or else the plan it is on
+pub fn for_renewal(env: &Env, sub: &Subscription) -> Result<Plan, Error> {
+    get_active(env, pending(env, &sub.user).unwrap_or(sub.plan_id))
+}
+
+// Validate the length of a plan's billing period
+pub fn validate_duration(duration_days: u32) -> Result<(), Error> {
+    if duration_days == 0 {
+        return Err(Error::InvalidInput);
+    }
+    Ok(())
+}
+
+// Validate a plan price
+pub fn validate_price(price: i128) -> Result<(), Error> {
+    if price <= 0 {
+        return Err(Error::InvalidInput);
+    }
+    Ok(())
+}
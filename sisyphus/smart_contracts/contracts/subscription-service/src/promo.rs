@@ -0,0 +1,73 @@
+use soroban_sdk::xdr::ToXdr;
+use soroban_sdk::{Address, Bytes, BytesN, Env};
+use crate::types::{DataKey, Discount, PromoCode};
+use crate::errors::Error;
+use crate::ttl;
+
+// Plan ID a code valid on every plan is registered for; catalog IDs start at 1
+pub const ALL_PLANS: u32 = 0;
+
+// Codes are only ever stored as a SHA-256 hash, salted with this contract's address and the
+// plan the code is for, so the same code hashes differently on another deployment or plan.
+// Salting does not make a short code hard to guess, and each redemption carries the code in
+// its transaction arguments, so a code is effectively public once it has been used.
+pub fn hash(env: &Env, plan_id: u32, code: &Bytes) -> BytesN<32> {
+    let mut salted = env.current_contract_address().to_xdr(env);
+    salted.extend_from_array(&plan_id.to_be_bytes());
+    salted.append(code);
+    env.crypto().sha256(&salted).into()
+}
+
+pub fn get(env: &Env, code_hash: &BytesN<32>) -> Result<PromoCode, Error> {
+    ttl::persistent(env)
+        .get(&DataKey::Promo(code_hash.clone()))
+        .ok_or(Error::PromoNotFound)
+}
+
+pub fn save(env: &Env, promo: &PromoCode) {
+    ttl::persistent(env).set(&DataKey::Promo(promo.code_hash.clone()), promo);
+}
+
+// Validate the terms of a new code
+pub fn validate(discount: &Discount, max_redemptions: u32) -> Result<(), Error> {
+    let valid = match discount {
+        Discount::PercentOff(percent) => *percent > 0 && *percent <= 100,
+        Discount::AmountOff(amount) => *amount > 0,
+    };
+    if !valid || max_redemptions == 0 {
+        return Err(Error::InvalidInput);
+    }
+    Ok(())
+}
+
+// Find `code` for `plan_id`, or else for every plan, and check `user` may redeem it at `now`
+pub fn redeemable(env: &Env, code: &Bytes, user: &Address, plan_id: u32, now: u64) -> Result<PromoCode, Error> {
+    let promo = get(env, &hash(env, plan_id, code))
+        .or_else(|_| get(env, &hash(env, ALL_PLANS, code)))?;
+    if now >= promo.expires_at {
+        return Err(Error::PromoExpired);
+    }
+    if promo.redemptions >= promo.max_redemptions {
+        return Err(Error::PromoExhausted);
+    }
+    if ttl::persistent(env).has(&DataKey::PromoRedeemed(promo.code_hash.clone(), user.clone())) {
+        return Err(Error::PromoAlreadyRedeemed);
+    }
+    Ok(promo)
+}
+
+// Price after the discount, never below zero
+pub fn apply(discount: &Discount, price: i128) -> i128 {
+    match discount {
+        Discount::PercentOff(percent) => price - price * *percent as i128 / 100,
+        Discount::AmountOff(amount) => (price - amount).max(0),
+    }
+}
+
+// Count a redemption and remember that `user` has used the code
+pub fn record_redemption(env: &Env, mut promo: PromoCode, user: &Address) {
+    promo.redemptions += 1;
+    ttl::persistent(env)
+        .set(&DataKey::PromoRedeemed(promo.code_hash.clone(), user.clone()), &true);
+    save(env, &promo);
+}
@@ -0,0 +1,110 @@
+use soroban_sdk::{Env, String};
+use crate::types::{DataKey, Subscription, SubscriptionStatus};
+use crate::errors::Error;
+use crate::ttl;
+use crate::events;
+
+// The subscription lifecycle. Every status change goes through `transition`,
+// so this table is the single place that decides what is allowed.
+//
+//   Active    -> PastDue | Expired | Cancelled | Paused
+//   PastDue   -> Active | Expired | Cancelled
+//   Paused    -> Active | Cancelled
+//   Expired   -> Active
+//   Cancelled -> Active | Refunded
+//   Refunded  -> Active
+pub fn can_transition(from: SubscriptionStatus, to: SubscriptionStatus) -> bool {
+    use SubscriptionStatus::*;
+    matches!(
+        (from, to),
+        (Active, PastDue | Expired | Cancelled | Paused)
+            | (PastDue, Active | Expired | Cancelled)
+            | (Paused, Active | Cancelled)
+            | (Expired, Active)
+            | (Cancelled, Active | Refunded)
+            | (Refunded, Active)
+    )
+}
+
+// Move a subscription to a new status, rejecting transitions outside the table.
+// Every successful transition emits a status event, and expiring also emits `expired`.
+pub fn transition(env: &Env, sub: &mut Subscription, to: SubscriptionStatus) -> Result<(), Error> {
+    if sub.status == to {
+        return Err(Error::StatusUnchanged);
+    }
+    if !can_transition(sub.status, to) {
+        return Err(Error::InvalidStatusTransition);
+    }
+    events::status_changed(env, &sub.user, sub.status, to);
+    sub.status = to;
+    if to == SubscriptionStatus::Expired {
+        events::expired(env, sub);
+    }
+    Ok(())
+}
+
+// Seconds a lapsed subscription stays PastDue before expiring; 0 until the admin sets it
+pub fn grace_period(env: &Env) -> u64 {
+    env.storage().instance().get(&DataKey::GracePeriod).unwrap_or(0)
+}
+
+// Longest a subscription may stay Paused, in seconds; 0 (the default) disables pausing
+pub fn max_pause(env: &Env) -> u64 {
+    env.storage().instance().get(&DataKey::MaxPause).unwrap_or(0)
+}
+
+// Whether a Paused subscription has used up the maximum pause at `now`
+pub fn pause_elapsed(env: &Env, sub: &Subscription, now: u64) -> bool {
+    let paused_at: Option<u64> = ttl::persistent(env).get(&DataKey::PausedAt(sub.user.clone()));
+    paused_at.is_some_and(|paused_at| now >= paused_at.saturating_add(max_pause(env)))
+}
+
+// Pause a subscription at `now`, stopping its expiry clock
+pub fn pause(env: &Env, sub: &mut Subscription, now: u64) -> Result<(), Error> {
+    transition(env, sub, SubscriptionStatus::Paused)?;
+    ttl::persistent(env).set(&DataKey::PausedAt(sub.user.clone()), &now);
+    Ok(())
+}
+
+// Resume a Paused subscription at `now`, pushing its period back by the time it was
+// paused, up to the maximum pause
+pub fn resume(env: &Env, sub: &mut Subscription, now: u64) -> Result<(), Error> {
+    transition(env, sub, SubscriptionStatus::Active)?;
+    let key = DataKey::PausedAt(sub.user.clone());
+    let paused_at: u64 = ttl::persistent(env).get(&key).unwrap_or(now);
+    sub.start_date += now.saturating_sub(paused_at).min(max_pause(env));
+    ttl::persistent(env).remove(&key);
+    Ok(())
+}
+
+// Active or PastDue subscriptions grant access until `grace` seconds after the period ends
+pub fn is_entitled(sub: &Subscription, now: u64, grace: u64) -> bool {
+    matches!(sub.status, SubscriptionStatus::Active | SubscriptionStatus::PastDue)
+        && now < sub.expires_at() + grace
+}
+
+// Statuses that no longer hold a live period, so a new subscription may be bought
+pub fn is_closed(status: SubscriptionStatus) -> bool {
+    matches!(
+        status,
+        SubscriptionStatus::Expired | SubscriptionStatus::Cancelled | SubscriptionStatus::Refunded
+    )
+}
+
+// Map a status string from the legacy storage layout to the enum
+pub fn from_legacy(env: &Env, status: &String) -> Result<SubscriptionStatus, Error> {
+    let known = [
+        ("Active", SubscriptionStatus::Active),
+        ("Expired", SubscriptionStatus::Expired),
+        ("Cancelled", SubscriptionStatus::Cancelled),
+        ("Paused", SubscriptionStatus::Paused),
+        ("PastDue", SubscriptionStatus::PastDue),
+        ("Refunded", SubscriptionStatus::Refunded),
+    ];
+    for (name, value) in known {
+        if *status == String::from_str(env, name) {
+            return Ok(value);
+        }
+    }
+    Err(Error::UnknownLegacyStatus)
+}
@@ -1,81 +1,426 @@
-use soroban_sdk::{contract, contractimpl, Address, Env, String, Vec};
-use crate::types::{Subscription, DataKey};
+use soroban_sdk::{contract, contractimpl, Address, Bytes, BytesN, Env, Map, String, Symbol, TryFromVal, Val, Vec};
+use crate::types::{
+    Config, DataKey, Discount, Entitlements, LegacySubscription, OracleConfig, Organization, PaymentKind, PaymentPage,
+    PaymentRecord, Plan, PromoCode, RenewalReport, Subscription, SubscriptionPage, SubscriptionStatus, TtlPolicy,
+};
 use crate::errors::Error;
+use crate::{admin, events, history, index, oracle, org, payment, plan, promo, status, ttl};
 
 #[contract]
 pub struct SubscriptionContract;
 
 #[contractimpl]
 impl SubscriptionContract {
-    // Initialize contract (optional, for future extensions)
-    pub fn init(env: Env) {
-        let storage = env.storage().persistent();
-        if !storage.has(&DataKey::AllSubscriptions) {
-            storage.set(&DataKey::AllSubscriptions, &Vec::<Subscription>::new(&env));
+    // Configure the catalog admin, the payment token (a Stellar Asset Contract) and treasury. Runs
+    // once, as part of the deployment, so the admin role cannot be claimed in between.
+    pub fn __constructor(env: Env, admin: Address, token: Address, treasury: Address) {
+        env.storage().instance().set(&DataKey::Config, &Config { admin, token, treasury });
+    }
+
+    // Offer the admin role to `new_admin` (admin only). The current admin keeps the role until
+    // `new_admin` accepts, and a later offer replaces this one.
+    pub fn transfer_admin(env: Env, new_admin: Address) -> Result<(), Error> {
+        admin::require_admin(&env)?;
+        env.storage().instance().set(&DataKey::PendingAdmin, &new_admin);
+        Ok(())
+    }
+
+    // Take over the admin role offered by `transfer_admin`. The new admin signs, so the role can
+    // only move to an address that controls its key.
+    pub fn accept_admin(env: Env) -> Result<(), Error> {
+        let new_admin = admin::pending_admin(&env).ok_or(Error::NoPendingAdmin)?;
+        new_admin.require_auth();
+
+        let mut config = payment::get_config(&env)?;
+        config.admin = new_admin;
+        env.storage().instance().set(&DataKey::Config, &config);
+        env.storage().instance().remove(&DataKey::PendingAdmin);
+        Ok(())
+    }
+
+    // Query the current admin and the address a handover is pending to, if any
+    pub fn get_admin(env: Env) -> Result<(Address, Option<Address>), Error> {
+        Ok((payment::get_config(&env)?.admin, admin::pending_admin(&env)))
+    }
+
+    // Stop or restart every mutation except the admin's (admin only). Reads keep working.
+    pub fn set_paused(env: Env, paused: bool) -> Result<(), Error> {
+        admin::require_admin(&env)?;
+        env.storage().instance().set(&DataKey::Paused, &paused);
+        Ok(())
+    }
+
+    pub fn is_paused(env: Env) -> bool {
+        admin::is_paused(&env)
+    }
+
+    // Replace the contract's code with an uploaded WASM (admin only). Storage is kept, so the
+    // new code must read the current layout.
+    pub fn upgrade(env: Env, new_wasm_hash: BytesN<32>) -> Result<(), Error> {
+        admin::require_admin(&env)?;
+        env.deployer().update_current_contract_wasm(new_wasm_hash);
+        Ok(())
+    }
+
+    // Set how long storage entries are kept live (admin only): entries with fewer than
+    // `threshold` ledgers left are extended to `extend_to` whenever they are read or written
+    pub fn set_ttl_policy(env: Env, threshold: u32, extend_to: u32) -> Result<(), Error> {
+        admin::require_admin(&env)?;
+        let policy = TtlPolicy { threshold, extend_to };
+        ttl::validate(&env, &policy)?;
+        env.storage().instance().set(&DataKey::TtlPolicy, &policy);
+        ttl::extend_instance(&env);
+        Ok(())
+    }
+
+    pub fn get_ttl_policy(env: Env) -> TtlPolicy {
+        ttl::policy(&env)
+    }
+
+    // Extend the contract instance and each of `keys` that exists, so records nobody has
+    // touched lately are not archived. Anyone may call this; keys that do not exist are skipped.
+    pub fn bump(env: Env, keys: Vec<DataKey>) {
+        ttl::extend_instance(&env);
+        for key in keys.iter() {
+            ttl::extend(&env, &key);
         }
     }
 
-    // Add a new subscription with payment
-    pub fn add_subscription(
+    // Add a plan to the catalog (admin only), priced in one accepted token, returning its ID.
+    // Prices in other tokens are added with `set_plan_price`.
+    pub fn create_plan(
         env: Env,
-        user: Address,
-        duration: u32,
-        payment_amount: i128,
-        transaction_id: String,
-    ) -> Result<(), Error> {
-        // Input validation
-        if duration == 0 || payment_amount <= 0 {
-            return Err(Error::InvalidInput);
+        name: String,
+        duration_days: u32,
+        price: i128,
+        asset: Address,
+    ) -> Result<u32, Error> {
+        admin::require_admin(&env)?;
+        plan::validate_duration(duration_days)?;
+        plan::validate_price(price)?;
+        if !payment::is_accepted(&env, &asset)? {
+            return Err(Error::UnsupportedAsset);
         }
 
-        let storage = env.storage().persistent();
-        let key = DataKey::Subscriptions(user.clone());
+        let new_plan = Plan {
+            id: plan::next_id(&env),
+            name,
+            duration_days,
+            prices: Map::from_array(&env, [(asset, price)]),
+            active: true,
+            max_patients: 0,
+            features: 0,
+        };
+        plan::save(&env, &new_plan);
 
-        // Check for existing subscription
-        if storage.has(&key) {
-            let sub: Subscription = storage.get(&key).unwrap();
-            if sub.status == String::from_str(&env, "Active") {
-                return Err(Error::ActiveSubscriptionExists);
+        Ok(new_plan.id)
+    }
+
+    // Change the name and billing period of a plan (admin only).
+    // Existing subscriptions keep the terms they were bought under until renewal.
+    pub fn update_plan(env: Env, plan_id: u32, name: String, duration_days: u32) -> Result<(), Error> {
+        admin::require_admin(&env)?;
+        plan::validate_duration(duration_days)?;
+
+        let mut existing = plan::get(&env, plan_id)?;
+        existing.name = name;
+        existing.duration_days = duration_days;
+        plan::save(&env, &existing);
+
+        Ok(())
+    }
+
+    // Set a plan's price in an accepted token, or stop selling it in that token with 0 (admin
+    // only). A plan keeps at least one price. Existing subscriptions pay the new price from
+    // their next renewal.
+    pub fn set_plan_price(env: Env, plan_id: u32, asset: Address, price: i128) -> Result<(), Error> {
+        admin::require_admin(&env)?;
+
+        let mut existing = plan::get(&env, plan_id)?;
+        if price == 0 {
+            if existing.prices.len() == 1 && existing.prices.contains_key(asset.clone()) {
+                return Err(Error::InvalidInput);
+            }
+            existing.prices.remove(asset);
+        } else {
+            plan::validate_price(price)?;
+            if !payment::is_accepted(&env, &asset)? {
+                return Err(Error::UnsupportedAsset);
             }
+            existing.prices.set(asset, price);
         }
+        plan::save(&env, &existing);
+
+        Ok(())
+    }
 
-        // Verify payment (simplified for testnet)
-        // In production, integrate with Stellar Horizon API to verify transaction
-        if transaction_id.is_empty() {
-            return Err(Error::InvalidTransactionId);
+    // Accept or stop accepting a token for payment (admin only). The token set at deployment is
+    // always accepted. Subscriptions paid in a token that is no longer accepted cannot renew.
+    pub fn set_asset_accepted(env: Env, asset: Address, accepted: bool) -> Result<(), Error> {
+        let config = admin::require_admin(&env)?;
+        if asset == config.token {
+            return Err(Error::InvalidInput);
         }
 
-        // Create new subscription
-        let subscription = Subscription {
-            user: user.clone(),
-            start_date: env.ledger().timestamp(),
-            duration,
-            status: String::from_str(&env, "Active"),
-            payment_amount,
-            payment_date: env.ledger().timestamp(),
-            transaction_id,
-        };
+        let key = DataKey::AcceptedAsset(asset);
+        if accepted {
+            env.storage().instance().set(&key, &true);
+        } else {
+            env.storage().instance().remove(&key);
+        }
+        Ok(())
+    }
 
-        // Store subscription
-        storage.set(&key, &subscription);
+    // Set what a plan allows (admin only). Applies to current subscribers immediately.
+    pub fn set_plan_entitlements(env: Env, plan_id: u32, max_patients: u32, features: u32) -> Result<(), Error> {
+        admin::require_admin(&env)?;
+
+        let mut existing = plan::get(&env, plan_id)?;
+        existing.max_patients = max_patients;
+        existing.features = features;
+        plan::save(&env, &existing);
+
+        Ok(())
+    }
+
+    // Stop selling a plan (admin only); current subscribers are unaffected until renewal
+    pub fn retire_plan(env: Env, plan_id: u32) -> Result<(), Error> {
+        admin::require_admin(&env)?;
+
+        let mut existing = plan::get(&env, plan_id)?;
+        existing.active = false;
+        plan::save(&env, &existing);
+
+        Ok(())
+    }
+
+    // Set how long after a period ends the subscription stays PastDue, with access, before
+    // expiring (admin only)
+    pub fn set_grace_period(env: Env, grace_period_secs: u64) -> Result<(), Error> {
+        admin::require_admin(&env)?;
+        env.storage().instance().set(&DataKey::GracePeriod, &grace_period_secs);
+        Ok(())
+    }
+
+    // Register a promo code by its salted hash (admin only), so the code never appears in ledger
+    // state until it is redeemed. A `plan_id` of 0 makes the code valid for every plan.
+    pub fn create_promo(
+        env: Env,
+        code_hash: BytesN<32>,
+        discount: Discount,
+        max_redemptions: u32,
+        expires_at: u64,
+        plan_id: u32,
+    ) -> Result<(), Error> {
+        admin::require_admin(&env)?;
+        promo::validate(&discount, max_redemptions)?;
+        if promo::get(&env, &code_hash).is_ok() {
+            return Err(Error::PromoExists);
+        }
+        if plan_id != promo::ALL_PLANS {
+            plan::get(&env, plan_id)?;
+        }
+
+        promo::save(&env, &PromoCode {
+            code_hash,
+            discount,
+            max_redemptions,
+            redemptions: 0,
+            expires_at,
+            plan_id,
+        });
+        Ok(())
+    }
+
+    // Query a promo code by hash
+    pub fn get_promo(env: Env, code_hash: BytesN<32>) -> Result<PromoCode, Error> {
+        promo::get(&env, &code_hash)
+    }
+
+    // The hash `create_promo` expects for `code` on `plan_id` (0 for every plan). Meant to be
+    // simulated rather than submitted, so the code stays out of the ledger.
+    pub fn get_promo_hash(env: Env, plan_id: u32, code: Bytes) -> BytesN<32> {
+        promo::hash(&env, plan_id, &code)
+    }
+
+    // Offer a free trial of `trial_days` on a plan, or withdraw it with 0 (admin only)
+    pub fn set_trial_days(env: Env, plan_id: u32, trial_days: u32) -> Result<(), Error> {
+        admin::require_admin(&env)?;
+        plan::get(&env, plan_id)?;
+
+        let key = DataKey::TrialDays(plan_id);
+        if trial_days == 0 {
+            ttl::persistent(&env).remove(&key);
+        } else {
+            ttl::persistent(&env).set(&key, &trial_days);
+        }
+        Ok(())
+    }
+
+    // Set the longest a subscription may stay Paused (admin only); 0 disables pausing
+    pub fn set_max_pause(env: Env, max_pause_secs: u64) -> Result<(), Error> {
+        admin::require_admin(&env)?;
+        env.storage().instance().set(&DataKey::MaxPause, &max_pause_secs);
+        Ok(())
+    }
 
-        // Update all subscriptions list
-        let mut all_subs: Vec<Subscription> = storage.get(&DataKey::AllSubscriptions).unwrap();
-        all_subs.push_back(subscription.clone());
-        storage.set(&DataKey::AllSubscriptions, &all_subs);
+    // Set the SEP-40 price feed used to convert US dollar plan prices, or remove it with None
+    // (admin only). Prices older than `max_age_secs` are rejected.
+    pub fn set_price_oracle(env: Env, oracle: Option<Address>, max_age_secs: u64) -> Result<(), Error> {
+        admin::require_admin(&env)?;
+        match oracle {
+            Some(address) => {
+                if max_age_secs == 0 {
+                    return Err(Error::InvalidInput);
+                }
+                let config = OracleConfig { address, max_age: max_age_secs };
+                env.storage().instance().set(&DataKey::PriceOracle, &config);
+            }
+            None => env.storage().instance().remove(&DataKey::PriceOracle),
+        }
+        Ok(())
+    }
+
+    // Price a plan in US cents, bought in any accepted token at the feed's rate, or withdraw
+    // the US dollar price with 0 (admin only)
+    pub fn set_plan_usd_price(env: Env, plan_id: u32, usd_cents: i128) -> Result<(), Error> {
+        admin::require_admin(&env)?;
+        plan::get(&env, plan_id)?;
+
+        let key = DataKey::UsdPrice(plan_id);
+        if usd_cents == 0 {
+            ttl::persistent(&env).remove(&key);
+        } else {
+            plan::validate_price(usd_cents)?;
+            ttl::persistent(&env).set(&key, &usd_cents);
+        }
+        Ok(())
+    }
 
+    // Allow or stop refunds on cancellation (admin only)
+    pub fn set_refunds_enabled(env: Env, enabled: bool) -> Result<(), Error> {
+        admin::require_admin(&env)?;
+        env.storage().instance().set(&DataKey::RefundsEnabled, &enabled);
         Ok(())
     }
 
-    // Renew an existing subscription
-    pub fn renew_subscription(
+    // Query a plan by ID
+    pub fn get_plan(env: Env, plan_id: u32) -> Result<Plan, Error> {
+        plan::get(&env, plan_id)
+    }
+
+    // Query a plan's price per period in US cents, if it has one
+    pub fn get_usd_price(env: Env, plan_id: u32) -> Option<i128> {
+        oracle::usd_price(&env, plan_id)
+    }
+
+    // What `add_subscription_at_rate` would charge for a plan in `asset` at the feed's latest rate
+    pub fn quote_usd_price(env: Env, plan_id: u32, asset: Address) -> Result<i128, Error> {
+        let usd_cents = oracle::usd_price(&env, plan_id).ok_or(Error::PriceUnavailable)?;
+        if !payment::is_accepted(&env, &asset)? {
+            return Err(Error::UnsupportedAsset);
+        }
+        oracle::convert(&env, usd_cents, &asset)
+    }
+
+    // Add a new subscription, charging the plan price in `asset`. An organization's owner pays
+    // it for every seat.
+    pub fn add_subscription(env: Env, user: Address, plan_id: u32, asset: Address) -> Result<(), Error> {
+        admin::require_not_paused(&env)?;
+
+        // The user authorizes the purchase and the token transfer it makes
+        user.require_auth();
+
+        let plan = plan::get_active(&env, plan_id)?;
+        let price = plan::price(&plan, &asset)? * org::next_seats(&env, &user) as i128;
+        Self::open_subscription(&env, &user, &plan, &asset, price, plan.duration_days)
+    }
+
+    // Add a new subscription to a plan priced in US dollars, paying in `asset` converted at the
+    // feed's latest rate. Fails with `SlippageExceeded` rather than charge more than
+    // `max_amount`. Returns the amount charged.
+    pub fn add_subscription_at_rate(
         env: Env,
         user: Address,
-        duration: u32,
-        payment_amount: i128,
-        transaction_id: String,
+        plan_id: u32,
+        asset: Address,
+        max_amount: i128,
+    ) -> Result<i128, Error> {
+        admin::require_not_paused(&env)?;
+        user.require_auth();
+
+        let plan = plan::get_active(&env, plan_id)?;
+        let usd_cents = oracle::usd_price(&env, plan_id).ok_or(Error::PriceUnavailable)?;
+        if !payment::is_accepted(&env, &asset)? {
+            return Err(Error::UnsupportedAsset);
+        }
+        let price = oracle::convert(&env, usd_cents, &asset)? * org::next_seats(&env, &user) as i128;
+        if price > max_amount {
+            return Err(Error::SlippageExceeded);
+        }
+
+        Self::open_subscription(&env, &user, &plan, &asset, price, plan.duration_days)?;
+        Ok(price)
+    }
+
+    // Add a new subscription, charging the plan price in `asset` less the promo code's discount.
+    // The code is looked up by its salted hash, for `plan_id` or else every plan, and counted as
+    // redeemed. Fixed-amount codes are denominated in the token set at deployment and only
+    // apply to purchases in it.
+    pub fn add_subscription_with_promo(
+        env: Env,
+        user: Address,
+        plan_id: u32,
+        asset: Address,
+        code: Bytes,
     ) -> Result<(), Error> {
-        let storage = env.storage().persistent();
+        admin::require_not_paused(&env)?;
+        user.require_auth();
+
+        let plan = plan::get_active(&env, plan_id)?;
+        let price = plan::price(&plan, &asset)? * org::next_seats(&env, &user) as i128;
+        let promo = promo::redeemable(&env, &code, &user, plan_id, env.ledger().timestamp())?;
+        if matches!(promo.discount, Discount::AmountOff(_)) && asset != payment::get_config(&env)?.token {
+            return Err(Error::PromoNotApplicable);
+        }
+
+        let price = promo::apply(&promo.discount, price);
+        Self::open_subscription(&env, &user, &plan, &asset, price, plan.duration_days)?;
+        promo::record_redemption(&env, promo, &user);
+
+        Ok(())
+    }
+
+    // Start a free trial of a plan that offers one; each address gets a single trial. `asset`
+    // is the token later renewals are charged in.
+    pub fn start_trial(env: Env, user: Address, plan_id: u32, asset: Address) -> Result<(), Error> {
+        admin::require_not_paused(&env)?;
+        user.require_auth();
+
+        let plan = plan::get_active(&env, plan_id)?;
+        plan::price(&plan, &asset)?;
+        let storage = ttl::persistent(&env);
+        let trial_days: u32 = storage.get(&DataKey::TrialDays(plan_id)).ok_or(Error::TrialUnavailable)?;
+        if storage.has(&DataKey::TrialUsed(user.clone())) {
+            return Err(Error::TrialAlreadyUsed);
+        }
+
+        Self::open_subscription(&env, &user, &plan, &asset, 0, trial_days)?;
+        storage.set(&DataKey::TrialUsed(user), &true);
+
+        Ok(())
+    }
+
+    // Renew an expired subscription under the plan it was bought with, or the plan a
+    // downgrade was scheduled to. A plan with no fixed price in the subscription's token
+    // renews at its US dollar price converted at the feed's latest rate.
+    pub fn renew_subscription(env: Env, user: Address) -> Result<(), Error> {
+        admin::require_not_paused(&env)?;
+
+        // The user authorizes the renewal and the token transfer it makes
+        user.require_auth();
+
+        let storage = ttl::persistent(&env);
         let key = DataKey::Subscriptions(user.clone());
 
         // Check if subscription exists
@@ -83,53 +428,380 @@ impl SubscriptionContract {
             return Err(Error::SubscriptionNotFound);
         }
 
-        // Validate inputs
-        if duration == 0 || payment_amount <= 0 {
-            return Err(Error::InvalidInput);
-        }
+        let mut sub: Subscription = storage.get(&key).unwrap();
 
-        // Verify payment (simplified for testnet)
-        if transaction_id.is_empty() {
-            return Err(Error::InvalidTransactionId);
+        // A paused subscription still has the rest of its period; resume_subscription restores it
+        if sub.status == SubscriptionStatus::Paused {
+            return Err(Error::SubscriptionPaused);
         }
 
-        let mut sub: Subscription = storage.get(&key).unwrap();
-
         // Check if subscription is expired
         let current_time = env.ledger().timestamp();
-        let expiry_time = sub.start_date + (sub.duration as u64 * 86_400); // Convert days to seconds
-        if current_time < expiry_time && sub.status == String::from_str(&env, "Active") {
-            return Err(Error::SubscriptionStillActive);
+        if sub.status == SubscriptionStatus::Active {
+            if current_time < sub.expires_at() {
+                return Err(Error::SubscriptionStillActive);
+            }
+            // The period ran out without update_status being called
+            status::transition(&env, &mut sub, SubscriptionStatus::Expired)?;
         }
+        status::transition(&env, &mut sub, SubscriptionStatus::Active)?;
+
+        // Renew at the plan's current terms
+        let plan = plan::for_renewal(&env, &sub)?;
+        let price = plan::renewal_price(&env, &plan, &sub.asset)? * org::next_seats(&env, &user) as i128;
+
+        // Pull the renewal payment from the user into the treasury
+        let payment_ledger = payment::collect(&env, &sub.asset, &user, price)?;
 
         // Update subscription
+        sub.plan_id = plan.id;
         sub.start_date = current_time;
-        sub.duration = duration;
-        sub.status = String::from_str(&env, "Active");
-        sub.payment_amount = payment_amount;
+        sub.duration = plan.duration_days;
+        sub.payment_amount = price;
         sub.payment_date = current_time;
-        sub.transaction_id = transaction_id;
+        sub.payment_ledger = payment_ledger;
 
         // Store updated subscription
         storage.set(&key, &sub);
+        plan::clear_pending(&env, &user);
+        org::apply_pending(&env, &user);
+        history::record_period(&env, &sub, PaymentKind::Renewal);
+        events::renewed(&env, &sub);
+
+        Ok(())
+    }
+
+    // Move an Active subscription to another plan. An upgrade (a higher price per day) starts a
+    // new period on the new plan now, charging its price less the unused value of the current
+    // period, or paying back the difference from the treasury if the credit is larger. A
+    // downgrade is scheduled for the next renewal, and changing back to the current plan
+    // withdraws it. An organization's owner pays the new price for every seat. Plans are priced
+    // as for renewals, converting a US dollar price when there is no fixed one, and an upgrade
+    // whose new price is above `max_amount` is rejected. Returns the amount charged, negative
+    // if credited.
+    pub fn change_plan(env: Env, user: Address, new_plan_id: u32, max_amount: i128) -> Result<i128, Error> {
+        admin::require_not_paused(&env)?;
+        user.require_auth();
+
+        let mut sub = Self::active_subscription(&env, &user)?;
+        let current_time = env.ledger().timestamp();
+        if new_plan_id == sub.plan_id {
+            if plan::pending(&env, &user).is_none() {
+                return Err(Error::PlanUnchanged);
+            }
+            plan::clear_pending(&env, &user);
+            return Ok(0);
+        }
+
+        let current = plan::get(&env, sub.plan_id)?;
+        let new_plan = plan::get_active(&env, new_plan_id)?;
+        let new_seat_price = plan::renewal_price(&env, &new_plan, &sub.asset)?;
+        let current_seat_price = plan::renewal_price(&env, &current, &sub.asset).unwrap_or(0);
+        if !plan::is_upgrade(&current, current_seat_price, &new_plan, new_seat_price) {
+            plan::schedule(&env, &user, new_plan.id);
+            return Ok(0);
+        }
+
+        let new_price = new_seat_price * org::seats(&env, &user) as i128;
+        if new_price > max_amount {
+            return Err(Error::SlippageExceeded);
+        }
+
+        let due = Self::start_repriced_period(&env, &mut sub, &new_plan, new_price, current_time)?;
+        plan::clear_pending(&env, &user);
+
+        Ok(due)
+    }
+
+    // Freeze an Active subscription: access stops and so does the expiry clock, until the user
+    // resumes or the maximum pause runs out
+    pub fn pause_subscription(env: Env, user: Address) -> Result<(), Error> {
+        admin::require_not_paused(&env)?;
+        user.require_auth();
+
+        if status::max_pause(&env) == 0 {
+            return Err(Error::PauseUnavailable);
+        }
+
+        let storage = ttl::persistent(&env);
+        let key = DataKey::Subscriptions(user);
+        let mut sub: Subscription = storage.get(&key).ok_or(Error::SubscriptionNotFound)?;
+        let current_time = env.ledger().timestamp();
+
+        if sub.status != SubscriptionStatus::Active {
+            return Err(Error::SubscriptionNotActive);
+        }
+        if current_time >= sub.expires_at() {
+            return Err(Error::SubscriptionExpired);
+        }
+
+        status::pause(&env, &mut sub, current_time)?;
+        storage.set(&key, &sub);
+        Ok(())
+    }
+
+    // Resume a Paused subscription, extending its end date by the time it was paused (at
+    // most the maximum pause)
+    pub fn resume_subscription(env: Env, user: Address) -> Result<(), Error> {
+        admin::require_not_paused(&env)?;
+        user.require_auth();
+
+        let storage = ttl::persistent(&env);
+        let key = DataKey::Subscriptions(user);
+        let mut sub: Subscription = storage.get(&key).ok_or(Error::SubscriptionNotFound)?;
+
+        if sub.status != SubscriptionStatus::Paused {
+            return Err(Error::SubscriptionNotPaused);
+        }
+
+        status::resume(&env, &mut sub, env.ledger().timestamp())?;
+        storage.set(&key, &sub);
+        Ok(())
+    }
+
+    // Plan a user's scheduled downgrade will switch them to at their next renewal, if any
+    pub fn get_pending_plan(env: Env, user: Address) -> Option<u32> {
+        plan::pending(&env, &user)
+    }
+
+    // Found an organization and buy its subscription, paying the plan price in `asset` for each
+    // of `seats`. The owner holds the first seat; members are added with `add_member` and are
+    // entitled while the owner's subscription is.
+    pub fn create_organization(env: Env, owner: Address, plan_id: u32, asset: Address, seats: u32) -> Result<(), Error> {
+        admin::require_not_paused(&env)?;
+        owner.require_auth();
+
+        if seats == 0 {
+            return Err(Error::InvalidInput);
+        }
+        if org::get(&env, &owner).is_ok() {
+            return Err(Error::OrganizationExists);
+        }
+        if org::owner_of(&env, &owner).is_some() {
+            return Err(Error::AlreadyMember);
+        }
+
+        let plan = plan::get_active(&env, plan_id)?;
+        let price = plan::price(&plan, &asset)? * seats as i128;
+        Self::open_subscription(&env, &owner, &plan, &asset, price, plan.duration_days)?;
+
+        let mut organization = Organization { owner: owner.clone(), seats, members: Vec::new(&env) };
+        org::add_member(&env, &mut organization, &owner);
+        org::save(&env, &organization);
+        Ok(())
+    }
 
-        // Update all subscriptions list
-        let mut all_subs: Vec<Subscription> = storage.get(&DataKey::AllSubscriptions).unwrap();
-        for i in 0..all_subs.len() {
-            if all_subs.get(i).unwrap().user == user {
-                all_subs.set(i, sub.clone());
-                break;
+    // Give `member` one of the organization's free seats (owner only). An address holds a seat
+    // in at most one organization.
+    pub fn add_member(env: Env, owner: Address, member: Address) -> Result<(), Error> {
+        admin::require_not_paused(&env)?;
+        owner.require_auth();
+
+        let mut organization = org::get(&env, &owner)?;
+        if org::owner_of(&env, &member).is_some() {
+            return Err(Error::AlreadyMember);
+        }
+        // A scheduled reduction must still fit everyone when it applies
+        if organization.members.len() >= org::next_seats(&env, &owner) {
+            return Err(Error::SeatsExhausted);
+        }
+
+        org::add_member(&env, &mut organization, &member);
+        org::save(&env, &organization);
+        Ok(())
+    }
+
+    // Free `member`'s seat (owner only). The owner keeps theirs.
+    pub fn remove_member(env: Env, owner: Address, member: Address) -> Result<(), Error> {
+        admin::require_not_paused(&env)?;
+        owner.require_auth();
+
+        let mut organization = org::get(&env, &owner)?;
+        if member == owner {
+            return Err(Error::InvalidInput);
+        }
+        let position = organization.members.first_index_of(&member).ok_or(Error::NotMember)?;
+
+        organization.members.remove(position);
+        ttl::persistent(&env).remove(&DataKey::MemberOf(member));
+        org::save(&env, &organization);
+        Ok(())
+    }
+
+    // Change how many seats an organization pays for, prorated like `change_plan`. Adding seats
+    // starts a new period now at the price for every seat, less the unused value of the current
+    // period. Removing seats is scheduled for the next renewal and needs members to fit;
+    // setting the current count withdraws it. Returns the amount charged, negative if credited.
+    pub fn set_seats(env: Env, owner: Address, seats: u32) -> Result<i128, Error> {
+        admin::require_not_paused(&env)?;
+        owner.require_auth();
+
+        let mut organization = org::get(&env, &owner)?;
+        let mut sub = Self::active_subscription(&env, &owner)?;
+        if seats < organization.members.len() {
+            return Err(Error::SeatsInUse);
+        }
+        if seats == organization.seats {
+            if org::pending_seats(&env, &owner).is_none() {
+                return Err(Error::SeatsUnchanged);
             }
+            org::clear_pending(&env, &owner);
+            return Ok(0);
         }
-        storage.set(&DataKey::AllSubscriptions, &all_subs);
+        if seats < organization.seats {
+            ttl::persistent(&env).set(&DataKey::PendingSeats(owner), &seats);
+            return Ok(0);
+        }
+
+        let plan = plan::get(&env, sub.plan_id)?;
+        let new_price = plan::renewal_price(&env, &plan, &sub.asset)? * seats as i128;
+        let due = Self::start_repriced_period(&env, &mut sub, &plan, new_price, env.ledger().timestamp())?;
+
+        organization.seats = seats;
+        org::save(&env, &organization);
+        org::clear_pending(&env, &owner);
+        Ok(due)
+    }
+
+    // Query an organization by its owner
+    pub fn get_organization(env: Env, owner: Address) -> Result<Organization, Error> {
+        org::get(&env, &owner)
+    }
+
+    // Seat count an owner has reduced to, applied at their next renewal, if any
+    pub fn get_pending_seats(env: Env, owner: Address) -> Option<u32> {
+        org::pending_seats(&env, &owner)
+    }
 
+    // Opt in or out of automatic renewal. Renewals are charged with `transfer_from`, so the
+    // user must also `approve` this contract as a spender of the payment token.
+    pub fn set_auto_renew(env: Env, user: Address, enabled: bool) -> Result<(), Error> {
+        admin::require_not_paused(&env)?;
+        user.require_auth();
+
+        let storage = ttl::persistent(&env);
+        if !storage.has(&DataKey::Subscriptions(user.clone())) {
+            return Err(Error::SubscriptionNotFound);
+        }
+
+        let key = DataKey::AutoRenew(user);
+        if enabled {
+            storage.set(&key, &true);
+        } else {
+            storage.remove(&key);
+        }
         Ok(())
     }
 
+    // Charge the auto-renewing subscriptions in `limit` index positions from `cursor` whose period
+    // has ended, plus PastDue ones being retried. Failed charges leave the subscription PastDue.
+    // Permissionless, so any keeper can walk the index with `next_cursor`.
+    pub fn process_renewals(env: Env, cursor: u32, limit: u32) -> Result<RenewalReport, Error> {
+        admin::require_not_paused(&env)?;
+        let storage = ttl::persistent(&env);
+        let current_time = env.ledger().timestamp();
+
+        let grace_period = status::grace_period(&env);
+
+        let due = index::page(&env, cursor, limit, |sub| Self::renewal_due(&env, sub, current_time))?;
+        let mut report = RenewalReport { renewed: 0, failed: 0, expired: 0, next_cursor: due.next_cursor };
+
+        for mut sub in due.subscriptions.iter() {
+            // A PastDue subscription is only retried until its grace period runs out
+            if sub.status == SubscriptionStatus::PastDue && current_time >= sub.expires_at() + grace_period {
+                status::transition(&env, &mut sub, SubscriptionStatus::Expired)?;
+                report.expired += 1;
+                storage.set(&DataKey::Subscriptions(sub.user.clone()), &sub);
+                continue;
+            }
+
+            match Self::charge_renewal(&env, &mut sub, current_time) {
+                Ok(()) => {
+                    report.renewed += 1;
+                    events::renewed(&env, &sub);
+                }
+                Err(_) => {
+                    report.failed += 1;
+                    if sub.status == SubscriptionStatus::Active {
+                        status::transition(&env, &mut sub, SubscriptionStatus::PastDue)?;
+                        events::past_due(&env, &sub.user);
+                    }
+                }
+            }
+            storage.set(&DataKey::Subscriptions(sub.user.clone()), &sub);
+        }
+
+        Ok(report)
+    }
+
+    // Cancel a subscription before its period ends. With `refund`, the unused part of an
+    // Active period is paid back pro rata from the treasury and the subscription ends Refunded.
+    // Returns the refunded amount.
+    pub fn cancel_subscription(env: Env, user: Address, refund: bool) -> Result<i128, Error> {
+        admin::require_not_paused(&env)?;
+        user.require_auth();
+
+        let storage = ttl::persistent(&env);
+        let key = DataKey::Subscriptions(user.clone());
+
+        if !storage.has(&key) {
+            return Err(Error::SubscriptionNotFound);
+        }
+
+        let mut sub: Subscription = storage.get(&key).unwrap();
+        let current_time = env.ledger().timestamp();
+
+        // Nothing is left to cancel once the period has run out
+        if sub.status == SubscriptionStatus::Expired
+            || (sub.status == SubscriptionStatus::Active && current_time >= sub.expires_at())
+        {
+            return Err(Error::SubscriptionExpired);
+        }
+        if refund && !payment::refunds_enabled(&env) {
+            return Err(Error::RefundsDisabled);
+        }
+
+        // Only a paid-up period has unused days to give back
+        let refund_amount = if refund && sub.status == SubscriptionStatus::Active {
+            payment::prorated_refund(&sub, current_time)
+        } else {
+            0
+        };
+
+        status::transition(&env, &mut sub, SubscriptionStatus::Cancelled)?;
+        if refund_amount > 0 {
+            let ledger = payment::refund(&env, &user, &sub.asset, refund_amount)?;
+            status::transition(&env, &mut sub, SubscriptionStatus::Refunded)?;
+            history::record(
+                &env,
+                &user,
+                &PaymentRecord {
+                    kind: PaymentKind::Refund,
+                    plan_id: sub.plan_id,
+                    amount: refund_amount,
+                    asset: sub.asset.clone(),
+                    period_start: current_time,
+                    period_end: sub.expires_at(),
+                    timestamp: current_time,
+                    ledger,
+                },
+            );
+        }
+
+        storage.set(&key, &sub);
+        storage.remove(&DataKey::AutoRenew(user.clone()));
+        storage.remove(&DataKey::PausedAt(user.clone()));
+        plan::clear_pending(&env, &user);
+        org::clear_pending(&env, &user);
+        events::cancelled(&env, &sub, refund_amount);
+
+        Ok(refund_amount)
+    }
+
     // Query subscription by user
     pub fn get_subscription(env: Env, user: Address) -> Result<Subscription, Error> {
         let key = DataKey::Subscriptions(user.clone());
-        let storage = env.storage().persistent();
+        let storage = ttl::persistent(&env);
 
         if !storage.has(&key) {
             return Err(Error::SubscriptionNotFound);
@@ -139,25 +811,31 @@ impl SubscriptionContract {
         Ok(sub)
     }
 
-    // Query all active subscriptions
-    pub fn get_active_subscriptions(env: Env) -> Vec<Subscription> {
-        let storage = env.storage().persistent();
-        let all_subs: Vec<Subscription> = storage.get(&DataKey::AllSubscriptions).unwrap();
+    // List subscriptions in index order, `limit` (at most `index::MAX_PAGE_SIZE`) positions from `cursor`
+    pub fn list_subscriptions(env: Env, cursor: u32, limit: u32) -> Result<SubscriptionPage, Error> {
+        index::page(&env, cursor, limit, |_| true)
+    }
+
+    // Like `list_subscriptions`, keeping only subscriptions that are currently active.
+    // A page can hold fewer than `limit` entries; keep following `next_cursor` until it is None.
+    pub fn list_active(env: Env, cursor: u32, limit: u32) -> Result<SubscriptionPage, Error> {
         let current_time = env.ledger().timestamp();
+        index::page(&env, cursor, limit, |sub| sub.is_active_at(current_time))
+    }
 
-        let mut active_subs = Vec::new(&env);
-        for sub in all_subs.iter() {
-            let expiry_time = sub.start_date + (sub.duration as u64 * 86_400);
-            if current_time < expiry_time && sub.status == String::from_str(&env, "Active") {
-                active_subs.push_back(sub);
-            }
-        }
-        active_subs
+    // A user's purchases, renewals and refunds, oldest first, `limit` (at most
+    // `index::MAX_PAGE_SIZE`) records from `cursor`
+    pub fn get_payment_history(env: Env, user: Address, cursor: u32, limit: u32) -> Result<PaymentPage, Error> {
+        history::page(&env, &user, cursor, limit)
     }
 
-    // Check and update subscription status
+    // Check and update subscription status: PastDue once the period has ended, Expired once
+    // the grace period after it has ended too. Permissionless, as it only applies what the
+    // ledger clock already decides.
     pub fn update_status(env: Env, user: Address) -> Result<(), Error> {
-        let storage = env.storage().persistent();
+        admin::require_not_paused(&env)?;
+
+        let storage = ttl::persistent(&env);
         let key = DataKey::Subscriptions(user.clone());
 
         if !storage.has(&key) {
@@ -166,23 +844,337 @@ impl SubscriptionContract {
 
         let mut sub: Subscription = storage.get(&key).unwrap();
         let current_time = env.ledger().timestamp();
-        let expiry_time = sub.start_date + (sub.duration as u64 * 86_400);
+        let mut changed = false;
+
+        // A pause ends by itself once it reaches the maximum length
+        if sub.status == SubscriptionStatus::Paused && status::pause_elapsed(&env, &sub, current_time) {
+            status::resume(&env, &mut sub, current_time)?;
+            changed = true;
+        }
 
-        if current_time >= expiry_time && sub.status != String::from_str(&env, "Expired") {
-            sub.status = String::from_str(&env, "Expired");
+        let target = if current_time >= sub.expires_at() + status::grace_period(&env) {
+            Some(SubscriptionStatus::Expired)
+        } else if current_time >= sub.expires_at() {
+            Some(SubscriptionStatus::PastDue)
+        } else {
+            None
+        };
+
+        if let Some(target) = target {
+            if sub.status != target && status::can_transition(sub.status, target) {
+                status::transition(&env, &mut sub, target)?;
+                changed = true;
+            }
+        }
+
+        if changed {
             storage.set(&key, &sub);
+        }
+        Ok(())
+    }
+
+    // Move a subscription to any status the state machine allows (admin only), e.g. to
+    // reinstate or cancel a subscription on a user's behalf
+    pub fn set_status(env: Env, user: Address, new_status: SubscriptionStatus) -> Result<(), Error> {
+        admin::require_admin(&env)?;
+
+        let storage = ttl::persistent(&env);
+        let key = DataKey::Subscriptions(user);
+        let mut sub: Subscription = storage.get(&key).ok_or(Error::SubscriptionNotFound)?;
+        let current_time = env.ledger().timestamp();
+
+        // Pausing and resuming keep the expiry clock in step, as for the user's own calls
+        match (sub.status, new_status) {
+            (SubscriptionStatus::Active, SubscriptionStatus::Paused) => status::pause(&env, &mut sub, current_time)?,
+            (SubscriptionStatus::Paused, SubscriptionStatus::Active) => status::resume(&env, &mut sub, current_time)?,
+            _ => status::transition(&env, &mut sub, new_status)?,
+        }
+        storage.set(&key, &sub);
+        if new_status == SubscriptionStatus::Cancelled {
+            events::cancelled(&env, &sub, 0);
+        }
+        Ok(())
+    }
+
+    // Whether the user currently has access: Active or PastDue, and within the period plus grace.
+    // Reads the clock directly, so it holds even if `update_status` has not been called. Members
+    // of an organization are entitled through its owner's subscription.
+    pub fn is_entitled(env: Env, user: Address) -> bool {
+        Self::entitling_subscription(&env, &user).is_some()
+    }
 
-            // Update all subscriptions list
-            let mut all_subs: Vec<Subscription> = storage.get(&DataKey::AllSubscriptions).unwrap();
-            for i in 0..all_subs.len() {
-                if all_subs.get(i).unwrap().user == user {
-                    all_subs.set(i, sub.clone());
-                    break;
+    // What the user's plan allows, or None if they are not entitled (see `is_entitled`)
+    pub fn get_entitlements(env: Env, user: Address) -> Option<Entitlements> {
+        let sub = Self::entitling_subscription(&env, &user)?;
+
+        // Retired plans keep serving their current subscribers
+        let plan = plan::get(&env, sub.plan_id).ok()?;
+        Some(Entitlements {
+            plan_id: plan.id,
+            max_patients: plan.max_patients,
+            features: plan.features,
+        })
+    }
+
+    // Rewrite subscriptions stored in the original layout to the current one, placing them on
+    // `plan_id`, and move users from the legacy AllSubscriptions list into the subscriber index
+    // (admin only). Records already in the current layout are left untouched; returns how many
+    // were rewritten.
+    pub fn migrate_statuses(env: Env, plan_id: u32) -> Result<u32, Error> {
+        admin::require_admin(&env)?;
+        plan::get(&env, plan_id)?;
+
+        let storage = ttl::persistent(&env);
+        let Some(all_subs) = storage.get::<Vec<Val>>(&DataKey::AllSubscriptions) else {
+            return Ok(0);
+        };
+        let mut migrated = 0;
+
+        for entry in all_subs.iter() {
+            let listed = if Self::is_legacy(&env, &entry) {
+                let legacy = LegacySubscription::try_from_val(&env, &entry).map_err(|_| Error::InvalidInput)?;
+                Self::from_legacy(&env, legacy, plan_id)?
+            } else {
+                Subscription::try_from_val(&env, &entry).map_err(|_| Error::InvalidInput)?
+            };
+
+            // The per-user record is authoritative; the list copy only fills in a missing one
+            let key = DataKey::Subscriptions(listed.user.clone());
+            match storage.get::<Val>(&key) {
+                Some(stored) => {
+                    if Self::is_legacy(&env, &stored) {
+                        let legacy = LegacySubscription::try_from_val(&env, &stored).map_err(|_| Error::InvalidInput)?;
+                        storage.set(&key, &Self::from_legacy(&env, legacy, plan_id)?);
+                        migrated += 1;
+                    }
                 }
+                None => storage.set(&key, &listed),
             }
-            storage.set(&DataKey::AllSubscriptions, &all_subs);
+
+            index::push(&env, &listed.user);
         }
 
+        storage.remove(&DataKey::AllSubscriptions);
+        Ok(migrated)
+    }
+
+    // Start a subscription to `plan` for `duration_days`, charging `price` (nothing when 0)
+    fn open_subscription(
+        env: &Env,
+        user: &Address,
+        plan: &Plan,
+        asset: &Address,
+        price: i128,
+        duration_days: u32,
+    ) -> Result<(), Error> {
+        let storage = ttl::persistent(env);
+        let key = DataKey::Subscriptions(user.clone());
+
+        // Check for existing subscription
+        let existing: Option<Subscription> = storage.get(&key);
+        if let Some(sub) = &existing {
+            if !status::is_closed(sub.status) {
+                return Err(Error::ActiveSubscriptionExists);
+            }
+        }
+
+        // Pull the price from the user into the treasury
+        let payment_ledger = if price > 0 {
+            payment::collect(env, asset, user, price)?
+        } else {
+            env.ledger().sequence()
+        };
+
+        // Create new subscription
+        let subscription = Subscription {
+            user: user.clone(),
+            plan_id: plan.id,
+            start_date: env.ledger().timestamp(),
+            duration: duration_days,
+            status: SubscriptionStatus::Active,
+            payment_amount: price,
+            asset: asset.clone(),
+            payment_date: env.ledger().timestamp(),
+            payment_ledger,
+        };
+
+        // Store subscription
+        storage.set(&key, &subscription);
+        if price > 0 {
+            history::record_period(env, &subscription, PaymentKind::Purchase);
+        }
+        events::created(env, &subscription);
+        org::apply_pending(env, user);
+
+        // Returning subscribers are already indexed; a downgrade left over from their
+        // previous subscription no longer applies
+        if existing.is_none() {
+            index::push(env, user);
+        } else {
+            plan::clear_pending(env, user);
+        }
+
+        Ok(())
+    }
+
+    // The subscription that entitles `user`: their own, or else that of the organization they
+    // hold a seat in
+    fn entitling_subscription(env: &Env, user: &Address) -> Option<Subscription> {
+        let storage = ttl::persistent(env);
+        let now = env.ledger().timestamp();
+        let grace_period = status::grace_period(env);
+        let entitling = |holder: Address| {
+            storage
+                .get::<Subscription>(&DataKey::Subscriptions(holder))
+                .filter(|sub| status::is_entitled(sub, now, grace_period))
+        };
+
+        entitling(user.clone()).or_else(|| entitling(org::owner_of(env, user)?))
+    }
+
+    // Load a subscription that is Active and within its period
+    fn active_subscription(env: &Env, user: &Address) -> Result<Subscription, Error> {
+        let sub: Subscription = ttl::persistent(env)
+            .get(&DataKey::Subscriptions(user.clone()))
+            .ok_or(Error::SubscriptionNotFound)?;
+        if sub.status != SubscriptionStatus::Active {
+            return Err(Error::SubscriptionNotActive);
+        }
+        if env.ledger().timestamp() >= sub.expires_at() {
+            return Err(Error::SubscriptionExpired);
+        }
+        Ok(sub)
+    }
+
+    // Start a new period on `plan` at `new_price` now, charging it less the unused value of the
+    // current period, or paying back the difference from the treasury if the credit is larger.
+    // Returns the amount charged, negative if credited.
+    fn start_repriced_period(
+        env: &Env,
+        sub: &mut Subscription,
+        plan: &Plan,
+        new_price: i128,
+        now: u64,
+    ) -> Result<i128, Error> {
+        let user = sub.user.clone();
+
+        // Settle the difference between the new price and what is left of the current period
+        let credit = payment::prorated_refund(sub, now);
+        let credited_until = sub.expires_at();
+        let due = new_price - credit;
+        let payment_ledger = if due > 0 {
+            payment::collect(env, &sub.asset, &user, due)?
+        } else if due < 0 {
+            let ledger = payment::refund(env, &user, &sub.asset, -due)?;
+            history::record(
+                env,
+                &user,
+                &PaymentRecord {
+                    kind: PaymentKind::Refund,
+                    plan_id: sub.plan_id,
+                    amount: -due,
+                    asset: sub.asset.clone(),
+                    period_start: now,
+                    period_end: credited_until,
+                    timestamp: now,
+                    ledger,
+                },
+            );
+            ledger
+        } else {
+            env.ledger().sequence()
+        };
+
+        // The new period is worth the full price, whatever part of it the credit covered
+        sub.plan_id = plan.id;
+        sub.start_date = now;
+        sub.duration = plan.duration_days;
+        sub.payment_amount = new_price;
+        sub.payment_date = now;
+        sub.payment_ledger = payment_ledger;
+
+        ttl::persistent(env).set(&DataKey::Subscriptions(user.clone()), &*sub);
+        if due > 0 {
+            history::record(
+                env,
+                &user,
+                &PaymentRecord {
+                    kind: PaymentKind::PlanChange,
+                    plan_id: sub.plan_id,
+                    amount: due,
+                    asset: sub.asset.clone(),
+                    period_start: sub.start_date,
+                    period_end: sub.expires_at(),
+                    timestamp: now,
+                    ledger: payment_ledger,
+                },
+            );
+        }
+        events::plan_changed(env, sub, due);
+
+        Ok(due)
+    }
+
+    fn renewal_due(env: &Env, sub: &Subscription, now: u64) -> bool {
+        let due = match sub.status {
+            SubscriptionStatus::Active => now >= sub.expires_at(),
+            SubscriptionStatus::PastDue => true,
+            _ => false,
+        };
+        due && ttl::persistent(env).has(&DataKey::AutoRenew(sub.user.clone()))
+    }
+
+    // Charge the plan's current price from the user's allowance and start the next period.
+    // A converted US dollar price is bounded by the allowance the user granted.
+    // An on-time renewal continues from the old expiry; a PastDue one starts now.
+    fn charge_renewal(env: &Env, sub: &mut Subscription, now: u64) -> Result<(), Error> {
+        let plan = plan::for_renewal(env, sub)?;
+        let price = plan::renewal_price(env, &plan, &sub.asset)? * org::next_seats(env, &sub.user) as i128;
+        let payment_ledger = payment::collect_approved(env, &sub.asset, &sub.user, price)?;
+
+        // Within the grace period the new period follows on from the old one. A PastDue or later
+        // renewal starts it now, rather than charging for a period that has mostly passed.
+        let start_date = if sub.status == SubscriptionStatus::PastDue || now > sub.expires_at() + status::grace_period(env) {
+            now
+        } else {
+            sub.expires_at()
+        };
+        if sub.status == SubscriptionStatus::PastDue {
+            status::transition(env, sub, SubscriptionStatus::Active)?;
+        }
+        sub.start_date = start_date;
+        sub.plan_id = plan.id;
+        sub.duration = plan.duration_days;
+        sub.payment_amount = price;
+        sub.payment_date = now;
+        sub.payment_ledger = payment_ledger;
+        plan::clear_pending(env, &sub.user);
+        org::apply_pending(env, &sub.user);
+        history::record_period(env, sub, PaymentKind::Renewal);
         Ok(())
     }
+
+    // Whether a stored record is in the original layout, the only one with a `transaction_id`.
+    // Decoding a record into the wrong struct traps instead of returning an error, so check first.
+    fn is_legacy(env: &Env, record: &Val) -> bool {
+        Map::<Symbol, Val>::try_from_val(env, record)
+            .map(|fields| fields.contains_key(Symbol::new(env, "transaction_id")))
+            .unwrap_or(false)
+    }
+
+    fn from_legacy(env: &Env, legacy: LegacySubscription, plan_id: u32) -> Result<Subscription, Error> {
+        Ok(Subscription {
+            user: legacy.user,
+            plan_id,
+            start_date: legacy.start_date,
+            duration: legacy.duration,
+            status: status::from_legacy(env, &legacy.status)?,
+            payment_amount: legacy.payment_amount,
+            // Before multi-asset pricing every payment was in the configured token
+            asset: payment::get_config(env)?.token,
+            payment_date: legacy.payment_date,
+            // Original payments were recorded by transaction ID, not settlement ledger
+            payment_ledger: 0,
+        })
+    }
 }
@@ -0,0 +1,69 @@
+use soroban_sdk::{Env, IntoVal, TryFromVal, Val};
+use storage_ttl::Policy;
+use crate::types::{DataKey, TtlPolicy};
+use crate::errors::Error;
+
+// The configured TTL policy, or the default
+pub fn policy(env: &Env) -> TtlPolicy {
+    let policy = current(env);
+    TtlPolicy { threshold: policy.threshold, extend_to: policy.extend_to }
+}
+
+fn current(env: &Env) -> Policy {
+    env.storage()
+        .instance()
+        .get::<_, TtlPolicy>(&DataKey::TtlPolicy)
+        .map(|policy| Policy { threshold: policy.threshold, extend_to: policy.extend_to })
+        .unwrap_or_default()
+}
+
+pub fn validate(env: &Env, policy: &TtlPolicy) -> Result<(), Error> {
+    if !(Policy { threshold: policy.threshold, extend_to: policy.extend_to }).is_valid(env) {
+        return Err(Error::InvalidInput);
+    }
+    Ok(())
+}
+
+// The instance holds the configuration
+pub fn extend_instance(env: &Env) {
+    current(env).extend_instance(env);
+}
+
+pub fn extend(env: &Env, key: &DataKey) -> bool {
+    current(env).extend(env, key)
+}
+
+// Persistent storage that extends every entry it reads or writes, and the instance with it
+pub struct Persistent {
+    env: Env,
+    policy: Policy,
+}
+
+pub fn persistent(env: &Env) -> Persistent {
+    let policy = current(env);
+    policy.extend_instance(env);
+    Persistent { env: env.clone(), policy }
+}
+
+impl Persistent {
+    pub fn has(&self, key: &DataKey) -> bool {
+        self.policy.extend(&self.env, key)
+    }
+
+    pub fn get<V: TryFromVal<Env, Val>>(&self, key: &DataKey) -> Option<V> {
+        let value = self.env.storage().persistent().get(key);
+        if value.is_some() {
+            self.policy.extend(&self.env, key);
+        }
+        value
+    }
+
+    pub fn set<V: IntoVal<Env, Val>>(&self, key: &DataKey, value: &V) {
+        self.env.storage().persistent().set(key, value);
+        self.policy.extend(&self.env, key);
+    }
+
+    pub fn remove(&self, key: &DataKey) {
+        self.env.storage().persistent().remove(key);
+    }
+}
@@ -1,19 +1,219 @@
-use soroban_sdk::{contracttype, Address, String};
+use soroban_sdk::{contracttype, Address, BytesN, Map, String, Vec};
 
 #[contracttype]
 #[derive(Clone, Debug, PartialEq)]
 pub struct Subscription {
     pub user: Address,          // User's public key
+    pub plan_id: u32,          // Plan the subscription was bought under
     pub start_date: u64,       // Unix timestamp
     pub duration: u32,         // Duration in days
-    pub status: String,        // Active or Expired
+    pub status: SubscriptionStatus, // Lifecycle state, see `status.rs`
+    pub payment_amount: i128,  // Amount transferred, in `asset` base units
+    pub asset: Address,        // Token the subscription is paid and renewed in
+    pub payment_date: u64,     // Payment timestamp
+    pub payment_ledger: u32,   // Ledger sequence the payment settled in
+}
+
+impl Subscription {
+    // Timestamp at which the current period ends
+    pub fn expires_at(&self) -> u64 {
+        self.start_date + (self.duration as u64 * 86_400) // Convert days to seconds
+    }
+
+    // Whether the subscription is Active and its period has not ended at `now`
+    pub fn is_active_at(&self, now: u64) -> bool {
+        self.status == SubscriptionStatus::Active && now < self.expires_at()
+    }
+}
+
+// One page of subscriptions read from the subscriber index
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubscriptionPage {
+    pub subscriptions: Vec<Subscription>, // Matching subscriptions in index order
+    pub next_cursor: Option<u32>,         // Cursor for the next page, None once the index is exhausted
+}
+
+// What a payment record paid for
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PaymentKind {
+    Purchase,                             // A new subscription was bought
+    Renewal,                              // A subscription was renewed for a new period
+    PlanChange,                           // An upgrade was charged, net of the unused period's credit
+    Refund,                               // Unused days were paid back to the user
+}
+
+// One entry of a user's append-only payment history
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PaymentRecord {
+    pub kind: PaymentKind,                // Purchase, renewal or refund
+    pub plan_id: u32,                     // Plan the payment was for
+    pub amount: i128,                     // Amount transferred, in the asset's smallest unit
+    pub asset: Address,                   // Token the payment was made in
+    pub period_start: u64,                // Start of the period paid for (or refunded)
+    pub period_end: u64,                  // End of the period paid for (or refunded)
+    pub timestamp: u64,                   // When the transfer was made
+    pub ledger: u32,                      // Ledger sequence the transfer settled in
+}
+
+// One page of a user's payment history
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PaymentPage {
+    pub records: Vec<PaymentRecord>,      // Payments in the order they were made
+    pub next_cursor: Option<u32>,         // Cursor for the next page, None once the history is exhausted
+}
+
+// Outcome of one `process_renewals` batch
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenewalReport {
+    pub renewed: u32,                     // Subscriptions charged for a new period
+    pub failed: u32,                      // Due subscriptions that could not be charged
+    pub expired: u32,                     // PastDue subscriptions expired at the end of their grace period
+    pub next_cursor: Option<u32>,         // Cursor for the next batch, None once the index is exhausted
+}
+
+// Payload of the `created`, `renewed`, `expired` and `cancelled` lifecycle events
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubscriptionEvent {
+    pub plan_id: u32,                     // Plan the subscription is under
+    pub amount: i128,                     // Amount charged, or refunded for `cancelled`
+    pub asset: Address,                   // Token `amount` is in
+    pub period_start: u64,                // Start of the current period
+    pub period_end: u64,                  // End of the current period
+}
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SubscriptionStatus {
+    Active,
+    Expired,
+    Cancelled,
+    Paused,
+    PastDue,
+    Refunded,
+}
+
+// Storage layout of the original release, before plans, token payments and typed statuses;
+// only read by `migrate_statuses`
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct LegacySubscription {
+    pub user: Address,          // User's public key
+    pub start_date: u64,       // Unix timestamp
+    pub duration: u32,         // Duration in days
+    pub status: String,        // "Active" or "Expired"
     pub payment_amount: i128,  // Payment in XLM (stroops)
     pub payment_date: u64,     // Payment timestamp
-    pub transaction_id: String, // Stellar transaction ID
+    pub transaction_id: String, // Caller-supplied Stellar transaction ID
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Plan {
+    pub id: u32,               // Catalog identifier
+    pub name: String,          // Display name, e.g. "Basic"
+    pub duration_days: u32,    // Length of one billing period in days
+    pub prices: Map<Address, i128>, // Price per period in each accepted token, in its base units
+    pub active: bool,          // Retired plans can no longer be bought
+    pub max_patients: u32,     // Patients a trainer on this plan may link; 0 for no limit
+    pub features: u32,         // Bitmask of feature flags, interpreted by the contracts that read it
+}
+
+// A clinic or other group whose owner pays one subscription covering a seat per member
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Organization {
+    pub owner: Address,        // Pays the organization's subscription and manages its members
+    pub seats: u32,            // Seats paid for in the current period
+    pub members: Vec<Address>, // Addresses holding a seat, starting with the owner
+}
+
+// What a subscriber's plan allows, as read by other contracts
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entitlements {
+    pub plan_id: u32,          // Plan the subscriber is on
+    pub max_patients: u32,     // 0 for no limit
+    pub features: u32,         // Feature flag bitmask
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Discount {
+    PercentOff(u32),           // Percentage of the price waived, 1 to 100
+    AmountOff(i128),           // Fixed amount waived, in the plan asset's base units
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PromoCode {
+    pub code_hash: BytesN<32>, // Salted SHA-256 of the code; the code itself is never stored
+    pub discount: Discount,    // Reduction applied to the first period
+    pub max_redemptions: u32,  // Total redemptions allowed across all users
+    pub redemptions: u32,      // Redemptions so far
+    pub expires_at: u64,       // Unix timestamp after which the code is rejected
+    pub plan_id: u32,          // Plan the code applies to; `promo::ALL_PLANS` for every plan
+}
+
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    pub admin: Address,         // Manages the plan catalog
+    pub token: Address,         // Default payment token (a Stellar Asset Contract), always accepted
+    pub treasury: Address,      // Receives subscription payments
+}
+
+// SEP-40 price feed used to convert US dollar plan prices into payment tokens
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct OracleConfig {
+    pub address: Address,       // Price feed contract; its base asset must be USD
+    pub max_age: u64,           // Oldest price accepted, in seconds before the current ledger
+}
+
+// How long storage entries are kept live, in ledgers
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TtlPolicy {
+    pub threshold: u32,         // Entries with fewer ledgers left than this are extended on access
+    pub extend_to: u32,         // Ledgers an extended entry is kept live for
 }
 
 #[contracttype]
+#[derive(Clone)]
 pub enum DataKey {
     Subscriptions(Address),     // Maps user address to their subscription
-    AllSubscriptions,          // List of all subscriptions
+    AllSubscriptions,          // Legacy list of all subscriptions, only read by `migrate_statuses`
+    Subscriber(u32),           // Maps index position to a subscriber address
+    SubscriberCount,           // Number of indexed subscribers
+    Indexed(Address),          // Present once a user has been added to the subscriber index
+    Config,                    // Payment configuration
+    Plan(u32),                 // Maps plan ID to its catalog entry
+    PlanCount,                 // Number of plans ever created
+    RefundsEnabled,            // Whether cancellations may refund unused days
+    AutoRenew(Address),        // Present while a user has opted into auto-renewal
+    GracePeriod,               // Seconds a lapsed subscription stays PastDue before it expires
+    Promo(BytesN<32>),         // Maps a code hash to its promo code
+    PromoRedeemed(BytesN<32>, Address), // Present once a user has redeemed a code
+    TrialDays(u32),            // Free trial length in days for a plan, if it offers one
+    TrialUsed(Address),        // Present once a user has started a free trial
+    Payment(Address, u32),     // Maps a user and history position to a payment record
+    PaymentCount(Address),     // Number of payments recorded for a user
+    PendingPlan(Address),      // Plan a user has downgraded to, applied at their next renewal
+    MaxPause,                  // Longest a subscription may stay Paused, in seconds
+    AcceptedAsset(Address),    // Present while a token besides `Config::token` is accepted for payment
+    PausedAt(Address),         // When a Paused subscription was paused
+    PriceOracle,               // Price feed for US dollar plan prices, if configured
+    UsdPrice(u32),             // Maps plan ID to its price per period in US cents, if it has one
+    Organization(Address),     // Maps an owner to the organization they pay for
+    MemberOf(Address),         // Maps a member to the owner of the organization they hold a seat in
+    PendingSeats(Address),     // Seat count an owner has reduced to, applied at their next renewal
+    PendingAdmin,              // Address offered the admin role, until it accepts
+    Paused,                    // Whether the admin has stopped all non-admin mutations
+    TtlPolicy,                 // How long storage entries are kept live, if the admin has set it
 }
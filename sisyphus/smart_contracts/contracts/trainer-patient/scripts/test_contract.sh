@@ -25,30 +25,36 @@ ADMIN="deployer"
 
 # Setup additional identities for testing
 echo "Setting up test identities..."
-# Generate random secret keys for trainer and patient
+# Generate random secret keys for the trainer, nutritionist and patient
 TRAINER_SECRET=$(soroban keys generate)
+NUTRITIONIST_SECRET=$(soroban keys generate)
 PATIENT_SECRET=$(soroban keys generate)
 
 # Extract public and secret keys
 TRAINER_PUBLIC=$(echo "$TRAINER_SECRET" | grep "Public" | awk '{print $3}')
 TRAINER_SECRET=$(echo "$TRAINER_SECRET" | grep "Secret" | awk '{print $3}')
+NUTRITIONIST_PUBLIC=$(echo "$NUTRITIONIST_SECRET" | grep "Public" | awk '{print $3}')
+NUTRITIONIST_SECRET=$(echo "$NUTRITIONIST_SECRET" | grep "Secret" | awk '{print $3}')
 PATIENT_PUBLIC=$(echo "$PATIENT_SECRET" | grep "Public" | awk '{print $3}')
 PATIENT_SECRET=$(echo "$PATIENT_SECRET" | grep "Secret" | awk '{print $3}')
 
 # Add identities to Soroban config
 soroban config identity add trainer --global --secret-key "$TRAINER_SECRET"
+soroban config identity add nutritionist --global --secret-key "$NUTRITIONIST_SECRET"
 soroban config identity add patient --global --secret-key "$PATIENT_SECRET"
 
 # Fund the accounts on testnet
 echo "Funding test accounts on testnet..."
 soroban config identity fund trainer --network $NETWORK
+soroban config identity fund nutritionist --network $NETWORK
 soroban config identity fund patient --network $NETWORK
 
 echo "Trainer address: $TRAINER_PUBLIC"
+echo "Nutritionist address: $NUTRITIONIST_PUBLIC"
 echo "Patient address: $PATIENT_PUBLIC"
 
-# Register trainer
-echo "1. Registering trainer..."
+# Register the trainer and the nutritionist
+echo "1. Registering trainer and nutritionist..."
 soroban contract invoke \
     --id "$CONTRACT_ID" \
     --source trainer \
@@ -57,7 +63,15 @@ soroban contract invoke \
     register_trainer \
     --trainer_address "$TRAINER_PUBLIC"
 
-echo "Trainer registered successfully!"
+soroban contract invoke \
+    --id "$CONTRACT_ID" \
+    --source nutritionist \
+    --network $NETWORK \
+    -- \
+    register_trainer \
+    --trainer_address "$NUTRITIONIST_PUBLIC"
+
+echo "Trainer and nutritionist registered successfully!"
 
 # Generate patient ID (hash of patient address)
 PATIENT_ID=$(soroban contract invoke \
@@ -70,17 +84,38 @@ PATIENT_ID=$(soroban contract invoke \
 
 echo "Patient ID (for reference): $PATIENT_ID"
 
-# Add patient to trainer
-echo "2. Adding patient to trainer..."
+# Invite the patient, who accepts both providers onto their care team
+echo "2. Adding trainer and nutritionist to the patient's care team..."
 soroban contract invoke \
     --id "$CONTRACT_ID" \
     --source trainer \
     --network $NETWORK \
     -- \
-    add_patient \
+    invite_patient \
     --trainer "$TRAINER_PUBLIC" \
     --patient_address "$PATIENT_PUBLIC" \
-    --patient_id "$PATIENT_ID"
+    --role Exercise
+
+soroban contract invoke \
+    --id "$CONTRACT_ID" \
+    --source nutritionist \
+    --network $NETWORK \
+    -- \
+    invite_patient \
+    --trainer "$NUTRITIONIST_PUBLIC" \
+    --patient_address "$PATIENT_PUBLIC" \
+    --role Nutrition
+
+for PROVIDER in "$TRAINER_PUBLIC" "$NUTRITIONIST_PUBLIC"; do
+    soroban contract invoke \
+        --id "$CONTRACT_ID" \
+        --source patient \
+        --network $NETWORK \
+        -- \
+        accept_invitation \
+        --patient "$PATIENT_PUBLIC" \
+        --trainer "$PROVIDER"
+done
 
 echo "Patient added successfully!"
 
@@ -102,11 +137,11 @@ echo "Exercise routine added successfully!"
 echo "4. Adding meal plan for patient..."
 soroban contract invoke \
     --id "$CONTRACT_ID" \
-    --source trainer \
+    --source nutritionist \
     --network $NETWORK \
     -- \
     update_meal_plan \
-    --trainer "$TRAINER_PUBLIC" \
+    --trainer "$NUTRITIONIST_PUBLIC" \
     --patient_id "$PATIENT_ID" \
     --plan "{\"name\":\"High Protein Diet\",\"description\":\"Meal plan focused on protein\",\"meals\":[{\"name\":\"Breakfast\",\"time\":\"8:00 AM\",\"foods\":[\"Eggs\",\"Oatmeal\",\"Protein Shake\"],\"calories\":500,\"notes\":\"Eat within 1 hour of waking up\"}],\"last_updated\":0}"
 
@@ -1,6 +1,8 @@
 use soroban_sdk::{Address, BytesN, Env};
 use crate::error::ContractError;
 use crate::storage;
+use crate::subscription::{Entitlements, SubscriptionClient};
+use crate::types::CareRole;
 
 pub struct AccessControl;
 
@@ -22,6 +24,27 @@ impl AccessControl {
         }
     }
 
+    // Reject trainer writes while the admin has paused the contract
+    pub fn require_not_paused(env: &Env) -> Result<(), ContractError> {
+        if storage::is_paused(env) {
+            return Err(ContractError::ContractPaused);
+        }
+        Ok(())
+    }
+
+    // Require `address` to have authorized the invocation, returning the ID derived from it.
+    // Every trainer and patient entrypoint authenticates its caller through here.
+    pub fn authenticate(env: &Env, address: &Address) -> Result<BytesN<32>, ContractError> {
+        address.require_auth();
+        storage::get_trainer_id_from_address(env, address).ok_or(ContractError::InternalError)
+    }
+
+    // Require a registered trainer to have authorized the invocation
+    pub fn authenticate_trainer(env: &Env, address: &Address) -> Result<BytesN<32>, ContractError> {
+        Self::authenticate(env, address)?;
+        Self::require_trainer(env, address)
+    }
+
     // Check if an address is a registered trainer
     pub fn is_trainer(env: &Env, address: &Address) -> bool {
         if let Some(trainer_id) = storage::get_trainer_id_from_address(env, address) {
@@ -42,24 +65,51 @@ impl AccessControl {
         }
     }
 
-    // Check if a trainer has access to a patient
+    // Require a trainer to hold an active subscription, when a subscription contract is configured.
+    // Returns the trainer's plan entitlements, or None if trainers are not gated.
+    pub fn require_subscription(
+        env: &Env,
+        trainer_address: &Address
+    ) -> Result<Option<Entitlements>, ContractError> {
+        let Some(subscription_contract) = storage::get_subscription_contract(env) else {
+            return Ok(None);
+        };
+
+        let client = SubscriptionClient::new(env, &subscription_contract);
+        client
+            .get_entitlements(trainer_address)
+            .map(Some)
+            .ok_or(ContractError::SubscriptionRequired)
+    }
+
+    // Check the authorizing trainer is on a patient's care team, returning their role
     pub fn trainer_has_patient_access(
         env: &Env,
         trainer_address: &Address,
         patient_id: &BytesN<32>
-    ) -> Result<(), ContractError> {
-        // Get trainer ID
-        let trainer_id = Self::require_trainer(env, trainer_address)?;
+    ) -> Result<CareRole, ContractError> {
+        // Authenticate the trainer and get their ID
+        let trainer_id = Self::authenticate_trainer(env, trainer_address)?;
         
-        // Get trainer info
-        let trainer_info = storage::get_trainer(env, &trainer_id)
-            .ok_or(ContractError::TrainerNotFound)?;
+        // Get the patient's care team
+        let patient_info = storage::get_patient(env, patient_id)
+            .ok_or(ContractError::PatientNotLinkedWithTrainer)?;
             
-        // Check if patient is linked to this trainer
-        if !trainer_info.patients.contains(patient_id) {
-            return Err(ContractError::PatientNotLinkedWithTrainer);
+        // Check the trainer is on it
+        patient_info.team.get(trainer_id).ok_or(ContractError::PatientNotLinkedWithTrainer)
+    }
+
+    // Check the authorizing trainer's role on a patient's care team allows an update
+    pub fn require_role(
+        env: &Env,
+        trainer_address: &Address,
+        patient_id: &BytesN<32>,
+        permits: fn(&CareRole) -> bool
+    ) -> Result<(), ContractError> {
+        let role = Self::trainer_has_patient_access(env, trainer_address, patient_id)?;
+        if !permits(&role) {
+            return Err(ContractError::RoleNotPermitted);
         }
-        
         Ok(())
     }
 
@@ -83,12 +133,13 @@ impl AccessControl {
         }
     }
 
-    // Check if a patient can access their own data
+    // Check the authorizing patient can access their own data
     pub fn patient_can_access_data(
         env: &Env,
         patient_address: &Address
     ) -> Result<BytesN<32>, ContractError> {
-        // Get patient ID and check they're registered
+        // Authenticate the patient, get their ID and check they're registered
+        Self::authenticate(env, patient_address)?;
         let patient_id = Self::require_patient(env, patient_address)?;
         
         Ok(patient_id)
@@ -9,17 +9,25 @@ pub enum ContractError {
     NotAdmin = 2,
     NotTrainer = 3,
     NotPatient = 4,
+    SubscriptionRequired = 5,
+    NoPendingAdmin = 6,
+    RoleNotPermitted = 7,
     
     // Registration errors
     AlreadyInitialized = 10,
     TrainerAlreadyRegistered = 11,
     PatientAlreadyLinked = 12,
+    PatientLimitReached = 13,
     
     // Runtime errors
     TrainerNotFound = 20,
     PatientNotFound = 21,
     PatientNotLinkedWithTrainer = 22,
     InvalidDataFormat = 23,
+    ContractPaused = 24,
+    InvalidTtlPolicy = 25,
+    InvitationNotFound = 26,
+    VersionNotFound = 27,
     
     // General errors
     InternalError = 100,
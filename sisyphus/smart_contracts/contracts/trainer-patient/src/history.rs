@@ -0,0 +1,77 @@
+use soroban_sdk::{BytesN, Env, IntoVal, TryFromVal, Val, Vec};
+use crate::error::ContractError;
+use crate::storage;
+
+// Largest page a history read may return
+pub const MAX_PAGE_SIZE: u32 = 100;
+
+// The records a patient keeps every version of
+#[derive(Clone, Copy)]
+pub enum History {
+    Routine,
+    MealPlan,
+    Progress,
+}
+
+// Append a version to one of the patient's histories, returning its number. Versions are
+// numbered from 0 in the order they were recorded.
+pub fn record<V: IntoVal<Env, Val>>(env: &Env, history: History, patient_id: &BytesN<32>, value: &V) -> u32 {
+    storage::push_history_version(env, history, patient_id, value)
+}
+
+// Read version `version` of one of the patient's histories
+pub fn version<V: TryFromVal<Env, Val>>(
+    env: &Env,
+    history: History,
+    patient_id: &BytesN<32>,
+    version: u32,
+) -> Result<V, ContractError> {
+    if version >= storage::get_history_len(env, history, patient_id) {
+        return Err(ContractError::VersionNotFound);
+    }
+    storage::get_history_version(env, history, patient_id, version)
+        .ok_or(ContractError::VersionNotFound)
+}
+
+// Read versions `cursor..cursor + limit`, oldest first, with the cursor of the next page
+pub fn page<V: IntoVal<Env, Val> + TryFromVal<Env, Val>>(
+    env: &Env,
+    history: History,
+    patient_id: &BytesN<32>,
+    cursor: u32,
+    limit: u32,
+) -> Result<(Vec<V>, Option<u32>), ContractError> {
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(ContractError::InvalidDataFormat);
+    }
+
+    let count = storage::get_history_len(env, history, patient_id);
+    let end = cursor.saturating_add(limit).min(count);
+
+    let mut versions = Vec::new(env);
+    for n in cursor..end {
+        versions.push_back(version(env, history, patient_id, n)?);
+    }
+
+    Ok((versions, if end < count { Some(end) } else { None }))
+}
+
+// Extend versions `cursor..cursor + limit` of each of the patient's histories, returning the
+// cursor of the next page while any history has more versions
+pub fn extend(env: &Env, patient_id: &BytesN<32>, cursor: u32, limit: u32) -> Result<Option<u32>, ContractError> {
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(ContractError::InvalidDataFormat);
+    }
+
+    let mut next_cursor = None;
+    for history in [History::Routine, History::MealPlan, History::Progress] {
+        let count = storage::get_history_len(env, history, patient_id);
+        let end = cursor.saturating_add(limit).min(count);
+        storage::extend_history_versions(env, history, patient_id, cursor, end);
+        if end < count {
+            next_cursor = Some(end);
+        }
+    }
+
+    Ok(next_cursor)
+}
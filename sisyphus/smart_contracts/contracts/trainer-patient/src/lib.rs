@@ -1,48 +1,197 @@
 #![no_std]
-use soroban_sdk::{contract, contractimpl, Address, BytesN, Env};
+use soroban_sdk::{contract, contractimpl, Address, BytesN, Env, Map, Vec};
 
 mod types;
 mod error;
 mod storage;
 mod trainer;
 mod patient;
-mod data;
-mod access;
+pub mod data;
+pub mod access;
+pub mod subscription;
+mod ttl;
+mod history;
 
-use types::{PatientData, ExerciseRoutine, MealPlan, ProgressUpdate};
+use types::{CareRole, PatientData, ExerciseRoutine, MealPlan, ProgressUpdate, RoutinePage, MealPlanPage, ProgressPage, TtlPolicy};
 use error::ContractError;
 use trainer::TrainerInterface;
 use patient::PatientInterface;
+use access::AccessControl;
 
 #[contract]
 pub struct TrainerPatientContract;
 
 #[contractimpl]
 impl TrainerPatientContract {
-    // Initialize contract
-    pub fn initialize(env: Env, admin: Address) -> Result<(), ContractError> {
-        // Verify the contract is not already initialized
-        if storage::get_admin(&env).is_some() {
-            return Err(ContractError::AlreadyInitialized);
+    // Set the contract administrator. Runs once, as part of the deployment, so the role cannot
+    // be claimed in between.
+    pub fn __constructor(env: Env, admin: Address) {
+        storage::set_admin(&env, &admin);
+    }
+
+    // Offer the admin role to `new_admin`. The current admin keeps it until `new_admin` accepts.
+    pub fn transfer_admin(env: Env, admin: Address, new_admin: Address) -> Result<(), ContractError> {
+        AccessControl::require_admin(&env, &admin)?;
+        admin.require_auth();
+
+        storage::set_pending_admin(&env, &new_admin);
+        Ok(())
+    }
+
+    // Take over the admin role offered by `transfer_admin`
+    pub fn accept_admin(env: Env, new_admin: Address) -> Result<(), ContractError> {
+        if storage::get_pending_admin(&env) != Some(new_admin.clone()) {
+            return Err(ContractError::NoPendingAdmin);
         }
+        new_admin.require_auth();
 
-        // Set the contract administrator
-        storage::set_admin(&env, &admin);
+        storage::set_admin(&env, &new_admin);
+        storage::remove_pending_admin(&env);
+        Ok(())
+    }
+
+    pub fn get_admin(env: Env) -> Option<Address> {
+        storage::get_admin(&env)
+    }
+
+    // Stop or restart trainer writes; patient reads and admin calls keep working
+    pub fn set_paused(env: Env, admin: Address, paused: bool) -> Result<(), ContractError> {
+        AccessControl::require_admin(&env, &admin)?;
+        admin.require_auth();
+
+        storage::set_paused(&env, paused);
+        Ok(())
+    }
+
+    pub fn is_paused(env: Env) -> bool {
+        storage::is_paused(&env)
+    }
+
+    // Replace the contract code with an uploaded WASM, keeping storage
+    pub fn upgrade(env: Env, admin: Address, new_wasm_hash: BytesN<32>) -> Result<(), ContractError> {
+        AccessControl::require_admin(&env, &admin)?;
+        admin.require_auth();
+
+        env.deployer().update_current_contract_wasm(new_wasm_hash);
+        Ok(())
+    }
+
+    // Set how long storage entries are kept live: entries with fewer than `threshold` ledgers
+    // left are extended to `extend_to` whenever they are read or written
+    pub fn set_ttl_policy(env: Env, admin: Address, threshold: u32, extend_to: u32) -> Result<(), ContractError> {
+        AccessControl::require_admin(&env, &admin)?;
+        admin.require_auth();
+
+        let policy = TtlPolicy { threshold, extend_to };
+        ttl::validate(&env, &policy)?;
+        storage::set_ttl_policy(&env, &policy);
+        ttl::extend_instance(&env);
+        Ok(())
+    }
+
+    pub fn get_ttl_policy(env: Env) -> TtlPolicy {
+        ttl::policy(&env)
+    }
+
+    // Extend the instance and every record kept under each trainer or patient ID, so data
+    // nobody has touched lately is not archived. History versions are left to `bump_history`.
+    // Anyone may call this.
+    pub fn bump(env: Env, ids: Vec<BytesN<32>>) {
+        ttl::extend_instance(&env);
+        for id in ids.iter() {
+            storage::extend_records(&env, &id);
+        }
+    }
+
+    // Extend versions `cursor..cursor + limit` (at most 100) of each of a patient's histories,
+    // returning the cursor of the next page. Anyone may call this.
+    pub fn bump_history(env: Env, patient_id: BytesN<32>, cursor: u32, limit: u32) -> Result<Option<u32>, ContractError> {
+        ttl::extend_instance(&env);
+        history::extend(&env, &patient_id, cursor, limit)
+    }
+
+    // Gate trainer writes on an active subscription in the given subscription-service contract
+    pub fn set_subscription_contract(
+        env: Env,
+        admin: Address,
+        subscription_contract: Address
+    ) -> Result<(), ContractError> {
+        AccessControl::require_admin(&env, &admin)?;
+        admin.require_auth();
+
+        storage::set_subscription_contract(&env, &subscription_contract);
         Ok(())
     }
 
     // Trainer operations
     pub fn register_trainer(env: Env, trainer_address: Address) -> Result<(), ContractError> {
+        AccessControl::require_not_paused(&env)?;
         TrainerInterface::register(&env, &trainer_address)
     }
 
+    // Enroll a patient directly; the patient must sign the same transaction to consent
     pub fn add_patient(
         env: Env, 
         trainer: Address, 
         patient_address: Address,
-        patient_id: BytesN<32>
+        role: CareRole,
+    ) -> Result<(), ContractError> {
+        AccessControl::require_not_paused(&env)?;
+        TrainerInterface::add_patient(&env, &trainer, &patient_address, role)
+    }
+
+    // Invite a patient into `role` on their care team; they join once they call `accept_invitation`
+    pub fn invite_patient(
+        env: Env,
+        trainer: Address,
+        patient_address: Address,
+        role: CareRole,
+    ) -> Result<(), ContractError> {
+        AccessControl::require_not_paused(&env)?;
+        TrainerInterface::invite_patient(&env, &trainer, &patient_address, role)
+    }
+
+    pub fn accept_invitation(env: Env, patient: Address, trainer: Address) -> Result<(), ContractError> {
+        AccessControl::require_not_paused(&env)?;
+        PatientInterface::accept_invitation(&env, &patient, &trainer)
+    }
+
+    pub fn decline_invitation(env: Env, patient: Address, trainer: Address) -> Result<(), ContractError> {
+        AccessControl::require_not_paused(&env)?;
+        PatientInterface::decline_invitation(&env, &patient, &trainer)
+    }
+
+    // Take `trainer` off the patient's care team; their records are kept, even once the team is empty
+    pub fn leave_trainer(env: Env, patient: Address, trainer: Address) -> Result<(), ContractError> {
+        AccessControl::require_not_paused(&env)?;
+        PatientInterface::leave_trainer(&env, &patient, &trainer)
+    }
+
+    // The role `trainer` has invited `patient` into, if that invitation is open
+    pub fn get_invitation(env: Env, patient: Address, trainer: Address) -> Option<CareRole> {
+        let patient_id = storage::get_patient_id_from_address(&env, &patient)?;
+        let trainer_id = storage::get_trainer_id_from_address(&env, &trainer)?;
+        storage::get_invitation(&env, &patient_id, &trainer_id)
+    }
+
+    // Take the signing trainer off a patient's care team. The patient's records are kept, even
+    // once the team is empty.
+    pub fn remove_patient(env: Env, trainer: Address, patient_id: BytesN<32>) -> Result<(), ContractError> {
+        AccessControl::require_not_paused(&env)?;
+        TrainerInterface::remove_patient(&env, &trainer, &patient_id)
+    }
+
+    // Hand `from`'s role on a patient's care team to `to`; both trainers and the patient must
+    // sign. The new trainer inherits the patient's records if `keep_history` is set.
+    pub fn transfer_patient(
+        env: Env,
+        from: Address,
+        to: Address,
+        patient: Address,
+        keep_history: bool
     ) -> Result<(), ContractError> {
-        TrainerInterface::add_patient(&env, &trainer, &patient_address, &patient_id)
+        AccessControl::require_not_paused(&env)?;
+        TrainerInterface::transfer_patient(&env, &from, &to, &patient, keep_history)
     }
 
     pub fn update_exercise_routine(
@@ -51,6 +200,7 @@ impl TrainerPatientContract {
         patient_id: BytesN<32>,
         routine: ExerciseRoutine
     ) -> Result<(), ContractError> {
+        AccessControl::require_not_paused(&env)?;
         TrainerInterface::update_exercise_routine(&env, &trainer, &patient_id, &routine)
     }
 
@@ -60,6 +210,7 @@ impl TrainerPatientContract {
         patient_id: BytesN<32>,
         plan: MealPlan
     ) -> Result<(), ContractError> {
+        AccessControl::require_not_paused(&env)?;
         TrainerInterface::update_meal_plan(&env, &trainer, &patient_id, &plan)
     }
 
@@ -69,10 +220,20 @@ impl TrainerPatientContract {
         patient_id: BytesN<32>,
         progress: ProgressUpdate
     ) -> Result<(), ContractError> {
+        AccessControl::require_not_paused(&env)?;
         TrainerInterface::update_progress(&env, &trainer, &patient_id, &progress)
     }
 
+    // Read a patient's data as any provider on their care team, observers included
+    pub fn get_patient_data(env: Env, trainer: Address, patient_id: BytesN<32>) -> Result<PatientData, ContractError> {
+        TrainerInterface::get_patient_data(&env, &trainer, &patient_id)
+    }
+
     // Patient operations
+    pub fn get_care_team(env: Env, patient: Address) -> Result<Map<BytesN<32>, CareRole>, ContractError> {
+        PatientInterface::get_care_team(&env, &patient)
+    }
+
     pub fn get_exercise_routine(
         env: Env,
         patient: Address,
@@ -100,6 +261,29 @@ impl TrainerPatientContract {
     ) -> Result<PatientData, ContractError> {
         PatientInterface::get_all_data(&env, &patient)
     }
+
+    // Every update a trainer makes is kept as a version, numbered from 0. The `list_*` calls
+    // read `limit` versions from `cursor`, oldest first, at most 100 at a time.
+    pub fn get_routine_version(env: Env, patient: Address, version: u32) -> Result<ExerciseRoutine, ContractError> {
+        PatientInterface::get_routine_version(&env, &patient, version)
+    }
+
+    pub fn list_routine_history(env: Env, patient: Address, cursor: u32, limit: u32) -> Result<RoutinePage, ContractError> {
+        PatientInterface::list_routine_history(&env, &patient, cursor, limit)
+    }
+
+    pub fn get_meal_plan_version(env: Env, patient: Address, version: u32) -> Result<MealPlan, ContractError> {
+        PatientInterface::get_meal_plan_version(&env, &patient, version)
+    }
+
+    pub fn list_meal_plan_history(env: Env, patient: Address, cursor: u32, limit: u32) -> Result<MealPlanPage, ContractError> {
+        PatientInterface::list_meal_plan_history(&env, &patient, cursor, limit)
+    }
+
+    // The patient's progress as a time series, in the order it was recorded
+    pub fn list_progress_history(env: Env, patient: Address, cursor: u32, limit: u32) -> Result<ProgressPage, ContractError> {
+        PatientInterface::list_progress_history(&env, &patient, cursor, limit)
+    }
     
     // Debug helper to get patient ID from address
     pub fn get_patient_id(env: Env, address: Address) -> BytesN<32> {
@@ -1,12 +1,70 @@
-use soroban_sdk::{Address, Env};
+use soroban_sdk::{Address, BytesN, Env, Map};
 use crate::error::ContractError;
-use crate::types::{PatientData, ExerciseRoutine, MealPlan, ProgressUpdate};
+use crate::types::{CareRole, RoutinePage, MealPlanPage, ProgressPage, PatientData, ExerciseRoutine, MealPlan, ProgressUpdate};
 use crate::storage;
 use crate::access::AccessControl;
+use crate::trainer::TrainerInterface;
+use crate::history::{self, History};
 
 pub struct PatientInterface;
 
 impl PatientInterface {
+    // Accept a trainer's invitation, adding them to the patient's care team
+    pub fn accept_invitation(
+        env: &Env,
+        patient_address: &Address,
+        trainer_address: &Address,
+    ) -> Result<(), ContractError> {
+        let patient_id = AccessControl::authenticate(env, patient_address)?;
+        let trainer_id = storage::get_trainer_id_from_address(env, trainer_address)
+            .ok_or(ContractError::InternalError)?;
+        let role = storage::get_invitation(env, &patient_id, &trainer_id)
+            .ok_or(ContractError::InvitationNotFound)?;
+
+        TrainerInterface::link_patient(env, trainer_address, patient_address, role)
+    }
+
+    // Turn down a trainer's invitation
+    pub fn decline_invitation(
+        env: &Env,
+        patient_address: &Address,
+        trainer_address: &Address,
+    ) -> Result<(), ContractError> {
+        let patient_id = AccessControl::authenticate(env, patient_address)?;
+        let trainer_id = storage::get_trainer_id_from_address(env, trainer_address)
+            .ok_or(ContractError::InternalError)?;
+        if storage::get_invitation(env, &patient_id, &trainer_id).is_none() {
+            return Err(ContractError::InvitationNotFound);
+        }
+        storage::remove_invitation(env, &patient_id, &trainer_id);
+
+        Ok(())
+    }
+
+    // Take a trainer off the patient's care team; their records are kept, even once the team is empty
+    pub fn leave_trainer(
+        env: &Env,
+        patient_address: &Address,
+        trainer_address: &Address,
+    ) -> Result<(), ContractError> {
+        let patient_id = AccessControl::patient_can_access_data(env, patient_address)?;
+        let trainer_id = storage::get_trainer_id_from_address(env, trainer_address)
+            .ok_or(ContractError::InternalError)?;
+        TrainerInterface::unlink_patient(env, &patient_id, &trainer_id)
+    }
+
+    // Get the providers on the patient's care team and their roles
+    pub fn get_care_team(
+        env: &Env,
+        patient_address: &Address,
+    ) -> Result<Map<BytesN<32>, CareRole>, ContractError> {
+        let patient_id = AccessControl::patient_can_access_data(env, patient_address)?;
+        let patient_info = storage::get_patient(env, &patient_id)
+            .ok_or(ContractError::PatientNotFound)?;
+
+        Ok(patient_info.team)
+    }
+
     // Get the patient's exercise routine
     pub fn get_exercise_routine(
         env: &Env,
@@ -59,24 +117,81 @@ impl PatientInterface {
     ) -> Result<PatientData, ContractError> {
         // Verify the patient can access their data
         let patient_id = AccessControl::patient_can_access_data(env, patient_address)?;
-        
-        // Get all data
-        let exercise_routine = storage::get_exercise_routine(env, &patient_id)
+
+        Ok(Self::patient_data(env, &patient_id))
+    }
+
+    // Get a past version of the patient's exercise routine
+    pub fn get_routine_version(
+        env: &Env,
+        patient_address: &Address,
+        version: u32,
+    ) -> Result<ExerciseRoutine, ContractError> {
+        let patient_id = AccessControl::patient_can_access_data(env, patient_address)?;
+        history::version(env, History::Routine, &patient_id, version)
+    }
+
+    // Page through the patient's exercise routines, oldest first
+    pub fn list_routine_history(
+        env: &Env,
+        patient_address: &Address,
+        cursor: u32,
+        limit: u32,
+    ) -> Result<RoutinePage, ContractError> {
+        let patient_id = AccessControl::patient_can_access_data(env, patient_address)?;
+        let (routines, next_cursor) = history::page(env, History::Routine, &patient_id, cursor, limit)?;
+        Ok(RoutinePage { routines, next_cursor })
+    }
+
+    // Get a past version of the patient's meal plan
+    pub fn get_meal_plan_version(
+        env: &Env,
+        patient_address: &Address,
+        version: u32,
+    ) -> Result<MealPlan, ContractError> {
+        let patient_id = AccessControl::patient_can_access_data(env, patient_address)?;
+        history::version(env, History::MealPlan, &patient_id, version)
+    }
+
+    // Page through the patient's meal plans, oldest first
+    pub fn list_meal_plan_history(
+        env: &Env,
+        patient_address: &Address,
+        cursor: u32,
+        limit: u32,
+    ) -> Result<MealPlanPage, ContractError> {
+        let patient_id = AccessControl::patient_can_access_data(env, patient_address)?;
+        let (plans, next_cursor) = history::page(env, History::MealPlan, &patient_id, cursor, limit)?;
+        Ok(MealPlanPage { plans, next_cursor })
+    }
+
+    // Page through every progress update recorded for the patient, oldest first
+    pub fn list_progress_history(
+        env: &Env,
+        patient_address: &Address,
+        cursor: u32,
+        limit: u32,
+    ) -> Result<ProgressPage, ContractError> {
+        let patient_id = AccessControl::patient_can_access_data(env, patient_address)?;
+        let (updates, next_cursor) = history::page(env, History::Progress, &patient_id, cursor, limit)?;
+        Ok(ProgressPage { updates, next_cursor })
+    }
+
+    // Combine a patient's records, defaulting any that are missing
+    pub fn patient_data(env: &Env, patient_id: &BytesN<32>) -> PatientData {
+        let exercise_routine = storage::get_exercise_routine(env, patient_id)
             .unwrap_or_else(|| ExerciseRoutine::new(env));
             
-        let meal_plan = storage::get_meal_plan(env, &patient_id)
+        let meal_plan = storage::get_meal_plan(env, patient_id)
             .unwrap_or_else(|| MealPlan::new(env));
             
-        let progress = storage::get_progress(env, &patient_id)
+        let progress = storage::get_progress(env, patient_id)
             .unwrap_or_else(|| ProgressUpdate::new(env));
         
-        // Combine data
-        let patient_data = PatientData {
+        PatientData {
             exercise_routine,
             meal_plan,
             progress,
-        };
-        
-        Ok(patient_data)
+        }
     }
-}
\ No newline at end of file
+}
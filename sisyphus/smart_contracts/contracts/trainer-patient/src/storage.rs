@@ -1,6 +1,8 @@
-use soroban_sdk::{Address, BytesN, Env, Symbol, symbol_short};
+use soroban_sdk::{Address, BytesN, Env, IntoVal, Map, Symbol, TryFromVal, Val, symbol_short};
 use soroban_sdk::xdr::ToXdr;
-use crate::types::{TrainerInfo, PatientInfo, ExerciseRoutine, MealPlan, ProgressUpdate};
+use crate::types::{CareRole, TrainerInfo, PatientInfo, LegacyPatientInfo, ExerciseRoutine, MealPlan, ProgressUpdate, TtlPolicy};
+use crate::ttl;
+use crate::history::History;
 
 // Storage identifiers
 const ADMIN: Symbol = symbol_short!("ADMIN");
@@ -9,6 +11,15 @@ const PATIENT: Symbol = symbol_short!("PATIENT");
 const EX_ROUTINE: Symbol = symbol_short!("EX_RTN");
 const MEAL_PLAN: Symbol = symbol_short!("MEAL_PLN");
 const PROGRESS: Symbol = symbol_short!("PROGRESS");
+const SUB_CONTRACT: Symbol = symbol_short!("SUB_CTR");
+const PENDING_ADMIN: Symbol = symbol_short!("PEND_ADM");
+const PAUSED: Symbol = symbol_short!("PAUSED");
+const TTL_POLICY: Symbol = symbol_short!("TTL_POL");
+const INVITATION: Symbol = symbol_short!("INVITE");
+const ROUTINE_HISTORY: Symbol = symbol_short!("RTN_HIST");
+const MEAL_PLAN_HISTORY: Symbol = symbol_short!("MEAL_HST");
+const PROGRESS_HISTORY: Symbol = symbol_short!("PROG_HST");
+const HISTORY_START: Symbol = symbol_short!("HIST_BEG");
 
 // Admin operations
 pub fn get_admin(env: &Env) -> Option<Address> {
@@ -19,55 +30,229 @@ pub fn set_admin(env: &Env, admin: &Address) {
     env.storage().instance().set(&ADMIN, admin);
 }
 
+// Address offered the admin role, until it accepts
+pub fn get_pending_admin(env: &Env) -> Option<Address> {
+    env.storage().instance().get(&PENDING_ADMIN)
+}
+
+pub fn set_pending_admin(env: &Env, new_admin: &Address) {
+    env.storage().instance().set(&PENDING_ADMIN, new_admin);
+}
+
+pub fn remove_pending_admin(env: &Env) {
+    env.storage().instance().remove(&PENDING_ADMIN);
+}
+
+// Emergency stop
+pub fn is_paused(env: &Env) -> bool {
+    env.storage().instance().get(&PAUSED).unwrap_or(false)
+}
+
+pub fn set_paused(env: &Env, paused: bool) {
+    env.storage().instance().set(&PAUSED, &paused);
+}
+
+// Storage TTL
+pub fn get_ttl_policy(env: &Env) -> Option<TtlPolicy> {
+    env.storage().instance().get(&TTL_POLICY)
+}
+
+pub fn set_ttl_policy(env: &Env, policy: &TtlPolicy) {
+    env.storage().instance().set(&TTL_POLICY, policy);
+}
+
+// Persistent entries are extended, with the instance, whenever they are read or written
+fn has<K: IntoVal<Env, Val>>(env: &Env, key: &K) -> bool {
+    ttl::current(env).has(env, key)
+}
+
+fn get<K: IntoVal<Env, Val>, V: TryFromVal<Env, Val>>(env: &Env, key: &K) -> Option<V> {
+    ttl::current(env).get(env, key)
+}
+
+fn put<K: IntoVal<Env, Val>, V: IntoVal<Env, Val>>(env: &Env, key: &K, value: &V) {
+    ttl::current(env).set(env, key, value);
+}
+
+// Extend every record kept under `id`, whether it is a trainer's or a patient's, including
+// open invitations and the length of each history. History versions are extended a page
+// at a time by `extend_history_versions`.
+pub fn extend_records(env: &Env, id: &BytesN<32>) {
+    for prefix in [TRAINER, PATIENT, EX_ROUTINE, MEAL_PLAN, PROGRESS, INVITATION] {
+        ttl::extend(env, &(prefix, id.clone()));
+    }
+    for history in [History::Routine, History::MealPlan, History::Progress] {
+        let prefix = history_prefix(history);
+        ttl::extend(env, &(prefix.clone(), id.clone()));
+        ttl::extend(env, &(HISTORY_START, prefix, id.clone()));
+    }
+}
+
+// Subscription gating
+pub fn get_subscription_contract(env: &Env) -> Option<Address> {
+    env.storage().instance().get(&SUB_CONTRACT)
+}
+
+pub fn set_subscription_contract(env: &Env, subscription_contract: &Address) {
+    env.storage().instance().set(&SUB_CONTRACT, subscription_contract);
+}
+
 // Trainer operations
 pub fn has_trainer(env: &Env, trainer_id: &BytesN<32>) -> bool {
-    env.storage().persistent().has(&(TRAINER, trainer_id))
+    has(env, &(TRAINER, trainer_id))
 }
 
 pub fn get_trainer(env: &Env, trainer_id: &BytesN<32>) -> Option<TrainerInfo> {
-    env.storage().persistent().get(&(TRAINER, trainer_id))
+    get(env, &(TRAINER, trainer_id))
 }
 
 pub fn put_trainer(env: &Env, trainer_id: &BytesN<32>, trainer_info: &TrainerInfo) {
-    env.storage().persistent().set(&(TRAINER, trainer_id), trainer_info);
+    put(env, &(TRAINER, trainer_id), trainer_info);
 }
 
 // Patient operations
 pub fn has_patient(env: &Env, patient_id: &BytesN<32>) -> bool {
-    env.storage().persistent().has(&(PATIENT, patient_id))
+    has(env, &(PATIENT, patient_id))
 }
 
+// Patients stored before care teams name a single `trainer` instead of a `team`. They are read
+// as a team of that trainer in the Exercise role, and stored in the current layout the next
+// time their team changes.
 pub fn get_patient(env: &Env, patient_id: &BytesN<32>) -> Option<PatientInfo> {
-    env.storage().persistent().get(&(PATIENT, patient_id))
+    let stored: Val = get(env, &(PATIENT, patient_id))?;
+    // Decoding a record into the wrong struct traps instead of failing, so check the layout first
+    let fields = Map::<Symbol, Val>::try_from_val(env, &stored).ok()?;
+    if fields.contains_key(symbol_short!("trainer")) {
+        let legacy = LegacyPatientInfo::try_from_val(env, &stored).ok()?;
+        let mut team = Map::new(env);
+        team.set(legacy.trainer, CareRole::Exercise);
+        return Some(PatientInfo { team });
+    }
+    PatientInfo::try_from_val(env, &stored).ok()
 }
 
 pub fn put_patient(env: &Env, patient_id: &BytesN<32>, patient_info: &PatientInfo) {
-    env.storage().persistent().set(&(PATIENT, patient_id), patient_info);
+    put(env, &(PATIENT, patient_id), patient_info);
+}
+
+// Open invitations to a patient's care team, by trainer, with the role each was offered.
+// Kept in one entry per patient so `extend_records` reaches them.
+fn get_invitations(env: &Env, patient_id: &BytesN<32>) -> Map<BytesN<32>, CareRole> {
+    get(env, &(INVITATION, patient_id)).unwrap_or_else(|| Map::new(env))
+}
+
+// Role a trainer has invited a patient to their care team in, until the patient accepts or declines
+pub fn get_invitation(env: &Env, patient_id: &BytesN<32>, trainer_id: &BytesN<32>) -> Option<CareRole> {
+    get_invitations(env, patient_id).get(trainer_id.clone())
+}
+
+pub fn put_invitation(env: &Env, patient_id: &BytesN<32>, trainer_id: &BytesN<32>, role: &CareRole) {
+    let mut invitations = get_invitations(env, patient_id);
+    invitations.set(trainer_id.clone(), *role);
+    put(env, &(INVITATION, patient_id), &invitations);
+}
+
+pub fn remove_invitation(env: &Env, patient_id: &BytesN<32>, trainer_id: &BytesN<32>) {
+    let mut invitations = get_invitations(env, patient_id);
+    if invitations.remove(trainer_id.clone()).is_none() {
+        return;
+    }
+    if invitations.is_empty() {
+        env.storage().persistent().remove(&(INVITATION, patient_id.clone()));
+    } else {
+        put(env, &(INVITATION, patient_id), &invitations);
+    }
 }
 
 // Patient data operations
 pub fn get_exercise_routine(env: &Env, patient_id: &BytesN<32>) -> Option<ExerciseRoutine> {
-    env.storage().persistent().get(&(EX_ROUTINE, patient_id))
+    get(env, &(EX_ROUTINE, patient_id))
 }
 
 pub fn put_exercise_routine(env: &Env, patient_id: &BytesN<32>, routine: &ExerciseRoutine) {
-    env.storage().persistent().set(&(EX_ROUTINE, patient_id), routine);
+    put(env, &(EX_ROUTINE, patient_id), routine);
 }
 
 pub fn get_meal_plan(env: &Env, patient_id: &BytesN<32>) -> Option<MealPlan> {
-    env.storage().persistent().get(&(MEAL_PLAN, patient_id))
+    get(env, &(MEAL_PLAN, patient_id))
 }
 
 pub fn put_meal_plan(env: &Env, patient_id: &BytesN<32>, plan: &MealPlan) {
-    env.storage().persistent().set(&(MEAL_PLAN, patient_id), plan);
+    put(env, &(MEAL_PLAN, patient_id), plan);
 }
 
 pub fn get_progress(env: &Env, patient_id: &BytesN<32>) -> Option<ProgressUpdate> {
-    env.storage().persistent().get(&(PROGRESS, patient_id))
+    get(env, &(PROGRESS, patient_id))
 }
 
 pub fn put_progress(env: &Env, patient_id: &BytesN<32>, progress: &ProgressUpdate) {
-    env.storage().persistent().set(&(PROGRESS, patient_id), progress);
+    put(env, &(PROGRESS, patient_id), progress);
+}
+
+// Patient record histories. How many versions were ever written is kept under
+// `(prefix, patient_id)` and the nth under `(prefix, patient_id, n)`; versions are never
+// changed once written. Clearing a history only moves its start, kept under
+// `(HISTORY_START, prefix, patient_id)`, past every version written so far, so it costs the
+// same however long the history is. The skipped versions are no longer read or extended and
+// are archived once their TTL runs out; later versions never reuse their numbers.
+fn history_prefix(history: History) -> Symbol {
+    match history {
+        History::Routine => ROUTINE_HISTORY,
+        History::MealPlan => MEAL_PLAN_HISTORY,
+        History::Progress => PROGRESS_HISTORY,
+    }
+}
+
+fn get_history_written(env: &Env, history: History, patient_id: &BytesN<32>) -> u32 {
+    get(env, &(history_prefix(history), patient_id)).unwrap_or(0)
+}
+
+fn get_history_start(env: &Env, history: History, patient_id: &BytesN<32>) -> u32 {
+    get(env, &(HISTORY_START, history_prefix(history), patient_id)).unwrap_or(0)
+}
+
+// Number of versions since the history was last cleared
+pub fn get_history_len(env: &Env, history: History, patient_id: &BytesN<32>) -> u32 {
+    get_history_written(env, history, patient_id) - get_history_start(env, history, patient_id)
+}
+
+pub fn get_history_version<V: TryFromVal<Env, Val>>(
+    env: &Env,
+    history: History,
+    patient_id: &BytesN<32>,
+    version: u32,
+) -> Option<V> {
+    let start = get_history_start(env, history, patient_id);
+    get(env, &(history_prefix(history), patient_id, start + version))
+}
+
+// Append a version, returning its number
+pub fn push_history_version<V: IntoVal<Env, Val>>(
+    env: &Env,
+    history: History,
+    patient_id: &BytesN<32>,
+    value: &V,
+) -> u32 {
+    let written = get_history_written(env, history, patient_id);
+    put(env, &(history_prefix(history), patient_id, written), value);
+    put(env, &(history_prefix(history), patient_id), &(written + 1));
+    written - get_history_start(env, history, patient_id)
+}
+
+// Extend versions `from..to` of a history
+pub fn extend_history_versions(env: &Env, history: History, patient_id: &BytesN<32>, from: u32, to: u32) {
+    let start = get_history_start(env, history, patient_id);
+    for version in from..to {
+        ttl::extend(env, &(history_prefix(history), patient_id.clone(), start + version));
+    }
+}
+
+// Empty a history, leaving its versions to be archived
+pub fn clear_history(env: &Env, history: History, patient_id: &BytesN<32>) {
+    let written = get_history_written(env, history, patient_id);
+    if written > 0 {
+        put(env, &(HISTORY_START, history_prefix(history), patient_id), &written);
+    }
 }
 
 // Utility function to get patient ID from address
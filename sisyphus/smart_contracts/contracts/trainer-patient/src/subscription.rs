@@ -0,0 +1,17 @@
+use soroban_sdk::{contractclient, contracttype, Address, Env};
+
+// Mirrors `Entitlements` in subscription-service
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entitlements {
+    pub plan_id: u32,
+    pub max_patients: u32, // 0 for no limit
+    pub features: u32,
+}
+
+// The subset of subscription-service that trainer-patient calls
+#[contractclient(name = "SubscriptionClient")]
+pub trait SubscriptionInterface {
+    // What the user's plan allows, or None without a paid-up (or in-grace) subscription
+    fn get_entitlements(env: Env, user: Address) -> Option<Entitlements>;
+}
@@ -1,24 +1,25 @@
 #![cfg(test)]
+extern crate std;
 
-use soroban_sdk::{Env, Address, BytesN, vec, Map, String};
-use soroban_sdk::testutils::Address as _;
+use soroban_sdk::{Env, Address, BytesN, IntoVal, vec, Map, String, Symbol};
+use soroban_sdk::testutils::{Address as _, AuthorizedFunction, AuthorizedInvocation, Ledger, MockAuth, MockAuthInvoke};
+use soroban_sdk::token::StellarAssetClient;
 use soroban_sdk::xdr::ToXdr;
-use crate::{TrainerPatientContract, types::{Exercise, ExerciseRoutine, Meal, MealPlan, ProgressUpdate}};
+use subscription_service::{SubscriptionContract, SubscriptionContractClient};
+use crate::{TrainerPatientContract, TrainerPatientContractClient, error::ContractError, types::{CareRole, Exercise, ExerciseRoutine, LegacyPatientInfo, Meal, MealPlan, PatientInfo, ProgressUpdate, RoutinePage, TrainerInfo}};
 
-// Helper function to setup a test environment with initialized contract
+// Helper function to setup a test environment with a deployed contract
 fn setup() -> (Env, Address, Address) {
     let env = Env::default();
     
-    // Register the contract
-    let contract_id = env.register(TrainerPatientContract, ());
-    
     // Generate a random admin address
     let admin = Address::generate(&env);
     
-    // Initialize contract
-    env.as_contract(&contract_id, || {
-        TrainerPatientContract::initialize(env.clone(), admin.clone())
-    }).unwrap();
+    // Register the contract
+    let contract_id = env.register(TrainerPatientContract, (admin.clone(),));
+    
+    // Patients consent to enrollment by signing
+    env.mock_all_auths();
     
     (env, contract_id, admin)
 }
@@ -26,20 +27,12 @@ fn setup() -> (Env, Address, Address) {
 #[test]
 fn test_initialize() {
     let env = Env::default();
-    let contract_id = env.register(TrainerPatientContract, ());
     let admin = Address::generate(&env);
-    
-    // Test successful initialization
-    let result = env.as_contract(&contract_id, || {
-        TrainerPatientContract::initialize(env.clone(), admin.clone())
-    });
-    assert!(result.is_ok());
-    
-    // Test initializing an already initialized contract (should fail)
-    let result = env.as_contract(&contract_id, || {
-        TrainerPatientContract::initialize(env.clone(), admin.clone())
-    });
-    assert!(result.is_err());
+
+    // The admin is set by the constructor, in the deployment itself
+    let contract_id = env.register(TrainerPatientContract, (admin.clone(),));
+    let stored = env.as_contract(&contract_id, || crate::storage::get_admin(&env));
+    assert_eq!(stored, Some(admin));
 }
 
 #[test]
@@ -67,16 +60,13 @@ fn test_add_patient() {
     
     // Add a patient
     let patient = Address::generate(&env);
-    // Create a patient ID by hashing the patient address
-    let patient_xdr = patient.clone().to_xdr(&env);
-    let patient_id = env.crypto().sha256(&patient_xdr);
     
     let result = env.as_contract(&contract_id, || {
         TrainerPatientContract::add_patient(
             env.clone(), 
             trainer.clone(), 
             patient.clone(),
-            BytesN::from_array(&env, &patient_id.to_array())
+            CareRole::Exercise
         )
     });
     
@@ -105,7 +95,7 @@ fn test_update_and_get_exercise_routine() {
             env.clone(), 
             trainer.clone(), 
             patient.clone(),
-            patient_bytes.clone()
+            CareRole::Exercise
         )
     }).unwrap();
     
@@ -174,7 +164,7 @@ fn test_update_and_get_meal_plan() {
             env.clone(), 
             trainer.clone(), 
             patient.clone(),
-            patient_bytes.clone()
+            CareRole::Nutrition
         )
     }).unwrap();
     
@@ -247,7 +237,7 @@ fn test_update_and_get_progress() {
             env.clone(), 
             trainer.clone(), 
             patient.clone(),
-            patient_bytes.clone()
+            CareRole::Exercise
         )
     }).unwrap();
     
@@ -301,6 +291,10 @@ fn test_get_all_data() {
     env.as_contract(&contract_id, || {
         TrainerPatientContract::register_trainer(env.clone(), trainer.clone())
     }).unwrap();
+    let nutritionist = Address::generate(&env);
+    env.as_contract(&contract_id, || {
+        TrainerPatientContract::register_trainer(env.clone(), nutritionist.clone())
+    }).unwrap();
     
     // Add a patient
     let patient = Address::generate(&env);
@@ -314,7 +308,15 @@ fn test_get_all_data() {
             env.clone(), 
             trainer.clone(), 
             patient.clone(),
-            patient_bytes.clone()
+            CareRole::Exercise
+        )
+    }).unwrap();
+    env.as_contract(&contract_id, || {
+        TrainerPatientContract::add_patient(
+            env.clone(), 
+            nutritionist.clone(), 
+            patient.clone(),
+            CareRole::Nutrition
         )
     }).unwrap();
     
@@ -372,7 +374,7 @@ fn test_get_all_data() {
     env.as_contract(&contract_id, || {
         TrainerPatientContract::update_meal_plan(
             env.clone(), 
-            trainer.clone(), 
+            nutritionist.clone(), 
             patient_bytes.clone(),
             plan.clone()
         )
@@ -456,16 +458,13 @@ fn test_access_control() {
     
     // Add a patient to test patient functions
     let patient = Address::generate(&env);
-    let patient_xdr = patient.clone().to_xdr(&env);
-    let patient_id = env.crypto().sha256(&patient_xdr);
-    let patient_bytes = BytesN::from_array(&env, &patient_id.to_array());
     
     env.as_contract(&contract_id, || {
         TrainerPatientContract::add_patient(
             env.clone(), 
             trainer.clone(), 
             patient.clone(),
-            patient_bytes.clone()
+            CareRole::Exercise
         )
     }).unwrap();
     
@@ -500,7 +499,7 @@ fn test_storage_functions() {
             env.clone(), 
             trainer.clone(), 
             patient.clone(),
-            patient_bytes.clone()
+            CareRole::Exercise
         )
     }).unwrap();
     
@@ -510,11 +509,11 @@ fn test_storage_functions() {
         assert!(patient_info.is_some());
         
         let info = patient_info.unwrap();
-        // Check that the trainer ID matches our trainer (after hashing)
+        // Check that our trainer (by hashed ID) is on the care team
         let trainer_xdr = trainer.clone().to_xdr(&env);
         let trainer_id = env.crypto().sha256(&trainer_xdr);
         let trainer_bytes = BytesN::from_array(&env, &trainer_id.to_array());
-        assert_eq!(info.trainer, trainer_bytes);
+        assert_eq!(info.team.get(trainer_bytes), Some(CareRole::Exercise));
     });
 }
 
@@ -647,4 +646,905 @@ fn test_data_store_functions() {
     );
     
     assert_eq!(updated_plan.meals.len(), 2);
-} 
\ No newline at end of file
+}
+
+// Helper function to register subscription-service in the same Env, with one plan on sale,
+// and configure the trainer-patient contract to gate trainers on it
+fn setup_with_subscriptions(env: &Env) -> (TrainerPatientContractClient<'_>, SubscriptionContractClient<'_>, StellarAssetClient<'_>, u32) {
+    env.mock_all_auths();
+
+    let admin = Address::generate(env);
+    let client = TrainerPatientContractClient::new(env, &env.register(TrainerPatientContract, (admin.clone(),)));
+
+    let token = env.register_stellar_asset_contract_v2(Address::generate(env)).address();
+    let subscriptions = SubscriptionContractClient::new(env, &env.register(SubscriptionContract, (admin.clone(), token.clone(), Address::generate(env))));
+    let plan_id = subscriptions.create_plan(&String::from_str(env, "Basic"), &30, &10_000_000, &token);
+
+    client.set_subscription_contract(&admin, &subscriptions.address);
+    (client, subscriptions, StellarAssetClient::new(env, &token), plan_id)
+}
+
+// Helper function to buy a subscription for a trainer
+fn subscribe(subscriptions: &SubscriptionContractClient, token: &StellarAssetClient, plan_id: u32, trainer: &Address) {
+    token.mint(trainer, &10_000_000);
+    subscriptions.add_subscription(trainer, &plan_id, &token.address);
+}
+
+#[test]
+fn test_register_trainer_requires_subscription() {
+    let env = Env::default();
+    let (client, subscriptions, token, plan_id) = setup_with_subscriptions(&env);
+    let trainer = Address::generate(&env);
+
+    // Unpaid trainers are rejected
+    let result = client.try_register_trainer(&trainer);
+    assert_eq!(result, Err(Ok(ContractError::SubscriptionRequired)));
+
+    subscribe(&subscriptions, &token, plan_id, &trainer);
+    assert!(client.try_register_trainer(&trainer).is_ok());
+}
+
+#[test]
+fn test_trainer_writes_require_active_subscription() {
+    let env = Env::default();
+    let (client, subscriptions, token, plan_id) = setup_with_subscriptions(&env);
+    let trainer = Address::generate(&env);
+    subscribe(&subscriptions, &token, plan_id, &trainer);
+    client.register_trainer(&trainer);
+
+    let patient = Address::generate(&env);
+    let patient_id = client.get_patient_id(&patient);
+    client.add_patient(&trainer, &patient, &CareRole::Exercise);
+
+    // Let the subscription lapse
+    env.ledger().with_mut(|li| {
+        li.timestamp += 30 * 86_400;
+    });
+
+    let other_patient = Address::generate(&env);
+    let result = client.try_add_patient(&trainer, &other_patient, &CareRole::Exercise);
+    assert_eq!(result, Err(Ok(ContractError::SubscriptionRequired)));
+
+    let routine = ExerciseRoutine::new(&env);
+    let result = client.try_update_exercise_routine(&trainer, &patient_id, &routine);
+    assert_eq!(result, Err(Ok(ContractError::SubscriptionRequired)));
+
+    // Patients can still read their data
+    assert!(client.try_get_exercise_routine(&patient).is_ok());
+
+    // Renewing restores access
+    token.mint(&trainer, &10_000_000);
+    subscriptions.renew_subscription(&trainer);
+    assert!(client.try_update_exercise_routine(&trainer, &patient_id, &routine).is_ok());
+}
+
+#[test]
+fn test_set_subscription_contract_not_admin() {
+    let env = Env::default();
+    let (client, subscriptions, _token, _plan_id) = setup_with_subscriptions(&env);
+
+    let result = client.try_set_subscription_contract(&Address::generate(&env), &subscriptions.address);
+    assert_eq!(result, Err(Ok(ContractError::NotAdmin)));
+}
+
+#[test]
+fn test_add_patient_respects_plan_limit() {
+    let env = Env::default();
+    let (client, subscriptions, token, plan_id) = setup_with_subscriptions(&env);
+    subscriptions.set_plan_entitlements(&plan_id, &2, &0);
+
+    let trainer = Address::generate(&env);
+    subscribe(&subscriptions, &token, plan_id, &trainer);
+    client.register_trainer(&trainer);
+
+    for _ in 0..2 {
+        let patient = Address::generate(&env);
+        client.add_patient(&trainer, &patient, &CareRole::Exercise);
+    }
+
+    // A third patient exceeds the Basic tier
+    let patient = Address::generate(&env);
+    let result = client.try_add_patient(&trainer, &patient, &CareRole::Exercise);
+    assert_eq!(result, Err(Ok(ContractError::PatientLimitReached)));
+
+    // Raising the tier limit makes room
+    subscriptions.set_plan_entitlements(&plan_id, &3, &0);
+    assert!(client.try_add_patient(&trainer, &patient, &CareRole::Exercise).is_ok());
+}
+
+#[test]
+fn test_two_step_admin_handover() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let new_admin = Address::generate(&env);
+    let client = TrainerPatientContractClient::new(&env, &env.register(TrainerPatientContract, (admin.clone(),)));
+
+    let result = client.try_accept_admin(&new_admin);
+    assert_eq!(result, Err(Ok(ContractError::NoPendingAdmin)));
+    let result = client.try_transfer_admin(&new_admin, &new_admin);
+    assert_eq!(result, Err(Ok(ContractError::NotAdmin)));
+
+    // The old admin keeps the role until the new one accepts
+    client.transfer_admin(&admin, &new_admin);
+    assert_eq!(client.get_admin(), Some(admin.clone()));
+    let result = client.try_accept_admin(&Address::generate(&env));
+    assert_eq!(result, Err(Ok(ContractError::NoPendingAdmin)));
+
+    client.accept_admin(&new_admin);
+    assert_eq!(client.get_admin(), Some(new_admin.clone()));
+    let result = client.try_set_paused(&admin, &true);
+    assert_eq!(result, Err(Ok(ContractError::NotAdmin)));
+    let result = client.try_upgrade(&admin, &BytesN::from_array(&env, &[0; 32]));
+    assert_eq!(result, Err(Ok(ContractError::NotAdmin)));
+}
+
+#[test]
+fn test_pause_blocks_trainer_writes() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let client = TrainerPatientContractClient::new(&env, &env.register(TrainerPatientContract, (admin.clone(),)));
+
+    let trainer = Address::generate(&env);
+    let patient = Address::generate(&env);
+    let patient_id = client.get_patient_id(&patient);
+    client.register_trainer(&trainer);
+    client.add_patient(&trainer, &patient, &CareRole::Exercise);
+
+    client.set_paused(&admin, &true);
+    assert!(client.is_paused());
+    let result = client.try_register_trainer(&Address::generate(&env));
+    assert_eq!(result, Err(Ok(ContractError::ContractPaused)));
+    let result = client.try_update_exercise_routine(&trainer, &patient_id, &ExerciseRoutine::new(&env));
+    assert_eq!(result, Err(Ok(ContractError::ContractPaused)));
+
+    // Patients can still read their data
+    assert!(client.try_get_all_data(&patient).is_ok());
+
+    client.set_paused(&admin, &false);
+    assert!(client.try_update_exercise_routine(&trainer, &patient_id, &ExerciseRoutine::new(&env)).is_ok());
+}
+
+// Close `days` worth of ledgers
+fn advance_days(env: &Env, days: u32) {
+    env.ledger().with_mut(|ledger| {
+        ledger.sequence_number += days * storage_ttl::DAY_IN_LEDGERS;
+        ledger.timestamp += days as u64 * 86_400;
+    });
+}
+
+// Ledgers left before a persistent entry of the contract is archived
+fn persistent_ttl(env: &Env, contract_id: &Address, key: &(soroban_sdk::Symbol, BytesN<32>)) -> u32 {
+    use soroban_sdk::testutils::storage::Persistent as _;
+    env.as_contract(contract_id, || env.storage().persistent().get_ttl(key))
+}
+
+#[test]
+fn test_patient_data_stays_live_while_read() {
+    use storage_ttl::DEFAULT_EXTEND_TO;
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(TrainerPatientContract, (Address::generate(&env),));
+    let client = TrainerPatientContractClient::new(&env, &contract_id);
+
+    let trainer = Address::generate(&env);
+    let patient = Address::generate(&env);
+    let patient_id = client.get_patient_id(&patient);
+    client.register_trainer(&trainer);
+    client.add_patient(&trainer, &patient, &CareRole::Exercise);
+    client.update_exercise_routine(&trainer, &patient_id, &ExerciseRoutine::new(&env));
+
+    let key = (soroban_sdk::symbol_short!("EX_RTN"), patient_id.clone());
+    assert_eq!(persistent_ttl(&env, &contract_id, &key), DEFAULT_EXTEND_TO);
+
+    // Reads extend the routine once it is under the threshold, so it outlives its first 30 days
+    advance_days(&env, 20);
+    client.get_exercise_routine(&patient);
+    assert_eq!(persistent_ttl(&env, &contract_id, &key), DEFAULT_EXTEND_TO);
+    advance_days(&env, 20);
+    assert!(client.try_get_exercise_routine(&patient).is_ok());
+    assert_eq!(persistent_ttl(&env, &contract_id, &key), DEFAULT_EXTEND_TO);
+}
+
+#[test]
+fn test_ttl_policy_and_bump() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(TrainerPatientContract, (admin.clone(),));
+    let client = TrainerPatientContractClient::new(&env, &contract_id);
+
+    let result = client.try_set_ttl_policy(&admin, &100, &100);
+    assert_eq!(result, Err(Ok(ContractError::InvalidTtlPolicy)));
+    let result = client.try_set_ttl_policy(&Address::generate(&env), &100, &1_000);
+    assert_eq!(result, Err(Ok(ContractError::NotAdmin)));
+    client.set_ttl_policy(&admin, &(2 * storage_ttl::DAY_IN_LEDGERS), &(10 * storage_ttl::DAY_IN_LEDGERS));
+    let policy = client.get_ttl_policy();
+    assert_eq!(policy.extend_to, 10 * storage_ttl::DAY_IN_LEDGERS);
+
+    let trainer = Address::generate(&env);
+    client.register_trainer(&trainer);
+    let trainer_id = client.get_patient_id(&trainer);
+    let key = (soroban_sdk::symbol_short!("TRAINER"), trainer_id.clone());
+    assert_eq!(persistent_ttl(&env, &contract_id, &key), policy.extend_to);
+
+    // Nobody touches the trainer, but anyone can keep it live
+    advance_days(&env, 9);
+    env.set_auths(&[]);
+    client.bump(&vec![&env, trainer_id.clone(), BytesN::from_array(&env, &[0; 32])]);
+    assert!(env.auths().is_empty());
+    assert_eq!(persistent_ttl(&env, &contract_id, &key), policy.extend_to);
+}
+
+#[test]
+fn test_bump_keeps_histories_and_invitations_live() {
+    let env = Env::default();
+    let (client, patient, patient_id, coach, _nutritionist, _observer) = setup_care_team(&env);
+    client.update_exercise_routine(&coach, &patient_id, &named_routine(&env, "Knee rehab"));
+    client.update_exercise_routine(&coach, &patient_id, &named_routine(&env, "Strength"));
+    let physio = Address::generate(&env);
+    client.register_trainer(&physio);
+    client.invite_patient(&physio, &patient, &CareRole::Observer);
+
+    // Only bump touches the patient's entries, past the default 30-day TTL
+    advance_days(&env, 20);
+    client.bump(&vec![&env, patient_id.clone()]);
+    assert_eq!(client.bump_history(&patient_id, &0, &1), Some(1));
+    assert_eq!(client.bump_history(&patient_id, &1, &1), None);
+    advance_days(&env, 20);
+
+    assert_eq!(client.get_routine_version(&patient, &0).name, String::from_str(&env, "Knee rehab"));
+    assert_eq!(client.get_invitation(&patient, &physio), Some(CareRole::Observer));
+}
+
+#[test]
+fn test_bump_history_pages() {
+    let env = Env::default();
+    let (client, patient, patient_id, coach, _nutritionist, _observer) = setup_care_team(&env);
+    for name in ["Week 1", "Week 2", "Week 3"] {
+        client.update_exercise_routine(&coach, &patient_id, &named_routine(&env, name));
+    }
+
+    assert_eq!(client.try_bump_history(&patient_id, &0, &0), Err(Ok(ContractError::InvalidDataFormat)));
+    assert_eq!(client.try_bump_history(&patient_id, &0, &101), Err(Ok(ContractError::InvalidDataFormat)));
+
+    // Versions are extended `limit` at a time
+    advance_days(&env, 20);
+    assert_eq!(client.bump_history(&patient_id, &0, &2), Some(2));
+    let version_ttl = |version: u32| {
+        use soroban_sdk::testutils::storage::Persistent;
+        let key = (soroban_sdk::symbol_short!("RTN_HIST"), patient_id.clone(), version);
+        env.as_contract(&client.address, || env.storage().persistent().get_ttl(&key))
+    };
+    assert_eq!(version_ttl(1), storage_ttl::DEFAULT_EXTEND_TO);
+    assert_eq!(version_ttl(2), storage_ttl::DEFAULT_EXTEND_TO - 20 * storage_ttl::DAY_IN_LEDGERS);
+
+    assert_eq!(client.bump_history(&patient_id, &2, &2), None);
+    assert_eq!(version_ttl(2), storage_ttl::DEFAULT_EXTEND_TO);
+    assert_eq!(client.get_routine_version(&patient, &2).name, String::from_str(&env, "Week 3"));
+}
+
+#[test]
+fn test_add_patient_requires_patient_consent() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(TrainerPatientContract, (Address::generate(&env),));
+    let client = TrainerPatientContractClient::new(&env, &contract_id);
+
+    let trainer = Address::generate(&env);
+    let patient = Address::generate(&env);
+    client.register_trainer(&trainer);
+
+    // Without the patient's signature the enrollment is rejected
+    env.set_auths(&[]);
+    assert!(client.try_add_patient(&trainer, &patient, &CareRole::Exercise).is_err());
+
+    env.mock_all_auths();
+    client.add_patient(&trainer, &patient, &CareRole::Exercise);
+    assert!(env.auths().iter().any(|(signer, _)| *signer == patient), "The patient should sign the enrollment");
+
+    // The patient is stored under the ID derived from their address, so their reads find it
+    let patient_id = client.get_patient_id(&patient);
+    env.as_contract(&contract_id, || assert!(crate::storage::has_patient(&env, &patient_id)));
+    assert!(client.try_get_all_data(&patient).is_ok());
+    assert!(client.try_update_progress(&trainer, &patient_id, &ProgressUpdate::new(&env)).is_ok());
+}
+
+#[test]
+fn test_patient_accepts_invitation() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let client = TrainerPatientContractClient::new(&env, &env.register(TrainerPatientContract, (Address::generate(&env),)));
+
+    let trainer = Address::generate(&env);
+    let patient = Address::generate(&env);
+    client.register_trainer(&trainer);
+
+    client.invite_patient(&trainer, &patient, &CareRole::Exercise);
+    assert_eq!(env.auths()[0].0, trainer, "The trainer should sign the invitation");
+    assert_eq!(client.get_invitation(&patient, &trainer), Some(CareRole::Exercise));
+    assert_eq!(client.try_get_all_data(&patient), Err(Ok(ContractError::NotPatient)), "Not enrolled before accepting");
+
+    client.accept_invitation(&patient, &trainer);
+    assert_eq!(env.auths()[0].0, patient, "The patient should sign the acceptance");
+    assert_eq!(client.get_invitation(&patient, &trainer), None);
+    assert!(client.try_get_all_data(&patient).is_ok());
+
+    // The invitation is used up, and enrolled patients cannot be invited again
+    assert_eq!(client.try_accept_invitation(&patient, &trainer), Err(Ok(ContractError::InvitationNotFound)));
+    assert_eq!(client.try_invite_patient(&trainer, &patient, &CareRole::Exercise), Err(Ok(ContractError::PatientAlreadyLinked)));
+}
+
+#[test]
+fn test_patient_declines_invitation() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let client = TrainerPatientContractClient::new(&env, &env.register(TrainerPatientContract, (Address::generate(&env),)));
+
+    let trainer = Address::generate(&env);
+    let patient = Address::generate(&env);
+    client.register_trainer(&trainer);
+    assert_eq!(
+        client.try_invite_patient(&Address::generate(&env), &patient, &CareRole::Exercise),
+        Err(Ok(ContractError::NotTrainer)),
+        "Only registered trainers can invite"
+    );
+
+    client.invite_patient(&trainer, &patient, &CareRole::Exercise);
+    client.decline_invitation(&patient, &trainer);
+    assert_eq!(client.get_invitation(&patient, &trainer), None);
+    assert_eq!(client.try_accept_invitation(&patient, &trainer), Err(Ok(ContractError::InvitationNotFound)));
+    assert_eq!(client.try_decline_invitation(&patient, &trainer), Err(Ok(ContractError::InvitationNotFound)));
+    assert_eq!(client.try_get_all_data(&patient), Err(Ok(ContractError::NotPatient)));
+}
+
+// The authorization tree of a direct call to `fn_name` on the contract with `args`
+fn direct_call(
+    env: &Env,
+    contract_id: &Address,
+    fn_name: &str,
+    args: soroban_sdk::Vec<soroban_sdk::Val>,
+) -> AuthorizedInvocation {
+    AuthorizedInvocation {
+        function: AuthorizedFunction::Contract((contract_id.clone(), Symbol::new(env, fn_name), args)),
+        sub_invocations: std::vec![],
+    }
+}
+
+#[test]
+fn test_trainer_calls_authenticate_trainer() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(TrainerPatientContract, (Address::generate(&env),));
+    let client = TrainerPatientContractClient::new(&env, &contract_id);
+
+    let trainer = Address::generate(&env);
+    let patient = Address::generate(&env);
+    let patient_id = client.get_patient_id(&patient);
+
+    client.register_trainer(&trainer);
+    assert_eq!(
+        env.auths(),
+        std::vec![(trainer.clone(), direct_call(&env, &contract_id, "register_trainer", (trainer.clone(),).into_val(&env)))]
+    );
+
+    // Direct enrollment needs both the trainer and the patient
+    client.add_patient(&trainer, &patient, &CareRole::Exercise);
+    let args: soroban_sdk::Vec<soroban_sdk::Val> = (trainer.clone(), patient.clone(), CareRole::Exercise).into_val(&env);
+    assert_eq!(
+        env.auths(),
+        std::vec![
+            (trainer.clone(), direct_call(&env, &contract_id, "add_patient", args.clone())),
+            (patient.clone(), direct_call(&env, &contract_id, "add_patient", args)),
+        ]
+    );
+
+    let routine = ExerciseRoutine::new(&env);
+    client.update_exercise_routine(&trainer, &patient_id, &routine);
+    let args = (trainer.clone(), patient_id.clone(), routine).into_val(&env);
+    assert_eq!(env.auths(), std::vec![(trainer.clone(), direct_call(&env, &contract_id, "update_exercise_routine", args))]);
+
+    let nutritionist = Address::generate(&env);
+    client.register_trainer(&nutritionist);
+    client.add_patient(&nutritionist, &patient, &CareRole::Nutrition);
+    let plan = MealPlan::new(&env);
+    client.update_meal_plan(&nutritionist, &patient_id, &plan);
+    let args = (nutritionist.clone(), patient_id.clone(), plan).into_val(&env);
+    assert_eq!(env.auths(), std::vec![(nutritionist.clone(), direct_call(&env, &contract_id, "update_meal_plan", args))]);
+
+    let progress = ProgressUpdate::new(&env);
+    client.update_progress(&trainer, &patient_id, &progress);
+    let args = (trainer.clone(), patient_id.clone(), progress).into_val(&env);
+    assert_eq!(env.auths(), std::vec![(trainer.clone(), direct_call(&env, &contract_id, "update_progress", args))]);
+}
+
+#[test]
+fn test_patient_calls_authenticate_patient() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(TrainerPatientContract, (Address::generate(&env),));
+    let client = TrainerPatientContractClient::new(&env, &contract_id);
+
+    let trainer = Address::generate(&env);
+    let patient = Address::generate(&env);
+    client.register_trainer(&trainer);
+
+    client.invite_patient(&trainer, &patient, &CareRole::Exercise);
+    let args = (trainer.clone(), patient.clone(), CareRole::Exercise).into_val(&env);
+    assert_eq!(env.auths(), std::vec![(trainer.clone(), direct_call(&env, &contract_id, "invite_patient", args))]);
+
+    // Accepting an invitation needs only the patient
+    client.accept_invitation(&patient, &trainer);
+    let args = (patient.clone(), trainer.clone()).into_val(&env);
+    assert_eq!(env.auths(), std::vec![(patient.clone(), direct_call(&env, &contract_id, "accept_invitation", args))]);
+
+    let args: soroban_sdk::Vec<soroban_sdk::Val> = (patient.clone(),).into_val(&env);
+
+    client.get_exercise_routine(&patient);
+    assert_eq!(env.auths(), std::vec![(patient.clone(), direct_call(&env, &contract_id, "get_exercise_routine", args.clone()))]);
+    client.get_meal_plan(&patient);
+    assert_eq!(env.auths(), std::vec![(patient.clone(), direct_call(&env, &contract_id, "get_meal_plan", args.clone()))]);
+    client.get_progress(&patient);
+    assert_eq!(env.auths(), std::vec![(patient.clone(), direct_call(&env, &contract_id, "get_progress", args.clone()))]);
+    client.get_all_data(&patient);
+    assert_eq!(env.auths(), std::vec![(patient.clone(), direct_call(&env, &contract_id, "get_all_data", args))]);
+}
+
+#[test]
+fn test_calls_fail_without_auth() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(TrainerPatientContract, (Address::generate(&env),));
+    let client = TrainerPatientContractClient::new(&env, &contract_id);
+
+    let trainer = Address::generate(&env);
+    let patient = Address::generate(&env);
+    let patient_id = client.get_patient_id(&patient);
+    client.register_trainer(&trainer);
+    client.add_patient(&trainer, &patient, &CareRole::Exercise);
+
+    env.set_auths(&[]);
+    assert!(client.try_register_trainer(&Address::generate(&env)).is_err());
+    assert!(client.try_add_patient(&trainer, &Address::generate(&env), &CareRole::Exercise).is_err());
+    assert!(client.try_invite_patient(&trainer, &Address::generate(&env), &CareRole::Exercise).is_err());
+    assert!(client.try_update_exercise_routine(&trainer, &patient_id, &ExerciseRoutine::new(&env)).is_err());
+    assert!(client.try_update_meal_plan(&trainer, &patient_id, &MealPlan::new(&env)).is_err());
+    assert!(client.try_update_progress(&trainer, &patient_id, &ProgressUpdate::new(&env)).is_err());
+    assert!(client.try_get_exercise_routine(&patient).is_err());
+    assert!(client.try_get_meal_plan(&patient).is_err());
+    assert!(client.try_get_progress(&patient).is_err());
+    assert!(client.try_get_all_data(&patient).is_err());
+
+    // Another address signing in the trainer's place is rejected too
+    let intruder = Address::generate(&env);
+    let routine = ExerciseRoutine::new(&env);
+    let result = client
+        .mock_auths(&[MockAuth {
+            address: &intruder,
+            invoke: &MockAuthInvoke {
+                contract: &contract_id,
+                fn_name: "update_exercise_routine",
+                args: (trainer.clone(), patient_id.clone(), routine.clone()).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .try_update_exercise_routine(&trainer, &patient_id, &routine);
+    assert!(result.is_err());
+}
+
+// Enroll a new patient with a registered trainer as their exercise trainer, returning the patient and their ID
+fn enroll_patient(env: &Env, client: &TrainerPatientContractClient, trainer: &Address) -> (Address, BytesN<32>) {
+    let patient = Address::generate(env);
+    client.add_patient(trainer, &patient, &CareRole::Exercise);
+    let patient_id = client.get_patient_id(&patient);
+    (patient, patient_id)
+}
+
+// IDs of the patients a trainer has
+fn trainer_patients(env: &Env, contract_id: &Address, client: &TrainerPatientContractClient, trainer: &Address) -> soroban_sdk::Vec<BytesN<32>> {
+    let trainer_id = client.get_patient_id(trainer);
+    env.as_contract(contract_id, || crate::storage::get_trainer(env, &trainer_id).unwrap().patients)
+}
+
+#[test]
+fn test_remove_patient() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(TrainerPatientContract, (Address::generate(&env),));
+    let client = TrainerPatientContractClient::new(&env, &contract_id);
+
+    let trainer = Address::generate(&env);
+    let other_trainer = Address::generate(&env);
+    client.register_trainer(&trainer);
+    client.register_trainer(&other_trainer);
+    let (patient, patient_id) = enroll_patient(&env, &client, &trainer);
+
+    assert_eq!(
+        client.try_remove_patient(&other_trainer, &patient_id),
+        Err(Ok(ContractError::PatientNotLinkedWithTrainer)),
+        "Only the patient's trainer can remove them"
+    );
+
+    client.remove_patient(&trainer, &patient_id);
+    let args = (trainer.clone(), patient_id.clone()).into_val(&env);
+    assert_eq!(env.auths(), std::vec![(trainer.clone(), direct_call(&env, &contract_id, "remove_patient", args))]);
+    assert!(trainer_patients(&env, &contract_id, &client, &trainer).is_empty());
+    assert!(client.get_care_team(&patient).is_empty());
+    assert_eq!(client.try_remove_patient(&trainer, &patient_id), Err(Ok(ContractError::PatientNotLinkedWithTrainer)));
+
+    // The patient keeps their records and is free to join another trainer
+    assert!(client.try_get_all_data(&patient).is_ok());
+    client.add_patient(&other_trainer, &patient, &CareRole::Exercise);
+    assert_eq!(client.get_care_team(&patient).len(), 1);
+}
+
+#[test]
+fn test_leave_trainer() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(TrainerPatientContract, (Address::generate(&env),));
+    let client = TrainerPatientContractClient::new(&env, &contract_id);
+
+    let trainer = Address::generate(&env);
+    client.register_trainer(&trainer);
+    let (patient, patient_id) = enroll_patient(&env, &client, &trainer);
+    let (_, other_id) = enroll_patient(&env, &client, &trainer);
+
+    client.leave_trainer(&patient, &trainer);
+    let args = (patient.clone(), trainer.clone()).into_val(&env);
+    assert_eq!(env.auths(), std::vec![(patient.clone(), direct_call(&env, &contract_id, "leave_trainer", args))]);
+    assert_eq!(trainer_patients(&env, &contract_id, &client, &trainer), vec![&env, other_id]);
+    assert!(client.get_care_team(&patient).is_empty());
+    let result = client.try_update_progress(&trainer, &patient_id, &ProgressUpdate::new(&env));
+    assert_eq!(result, Err(Ok(ContractError::PatientNotLinkedWithTrainer)));
+    assert_eq!(client.try_leave_trainer(&patient, &trainer), Err(Ok(ContractError::PatientNotLinkedWithTrainer)));
+
+    env.set_auths(&[]);
+    assert!(client.try_leave_trainer(&Address::generate(&env), &trainer).is_err());
+}
+
+#[test]
+fn test_transfer_patient() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(TrainerPatientContract, (Address::generate(&env),));
+    let client = TrainerPatientContractClient::new(&env, &contract_id);
+
+    let from = Address::generate(&env);
+    let to = Address::generate(&env);
+    client.register_trainer(&from);
+    client.register_trainer(&to);
+    let (patient, patient_id) = enroll_patient(&env, &client, &from);
+    let mut routine = ExerciseRoutine::new(&env);
+    routine.name = String::from_str(&env, "Knee rehab");
+    client.update_exercise_routine(&from, &patient_id, &routine);
+
+    // The new trainer inherits the routine
+    client.transfer_patient(&from, &to, &patient, &true);
+    let args: soroban_sdk::Vec<soroban_sdk::Val> = (from.clone(), to.clone(), patient.clone(), true).into_val(&env);
+    assert_eq!(
+        env.auths(),
+        std::vec![
+            (patient.clone(), direct_call(&env, &contract_id, "transfer_patient", args.clone())),
+            (from.clone(), direct_call(&env, &contract_id, "transfer_patient", args.clone())),
+            (to.clone(), direct_call(&env, &contract_id, "transfer_patient", args)),
+        ],
+        "Both trainers and the patient should sign the handoff"
+    );
+    assert!(trainer_patients(&env, &contract_id, &client, &from).is_empty());
+    assert_eq!(trainer_patients(&env, &contract_id, &client, &to), vec![&env, patient_id.clone()]);
+    assert_eq!(client.get_exercise_routine(&patient).name, routine.name);
+    let result = client.try_update_exercise_routine(&from, &patient_id, &routine);
+    assert_eq!(result, Err(Ok(ContractError::PatientNotLinkedWithTrainer)), "The old trainer loses access");
+    assert!(client.try_update_exercise_routine(&to, &patient_id, &routine).is_ok());
+
+    // Handing back without history starts from empty records
+    assert_eq!(client.try_transfer_patient(&to, &to, &patient, &false), Err(Ok(ContractError::PatientAlreadyLinked)));
+    client.transfer_patient(&to, &from, &patient, &false);
+    assert_eq!(client.get_exercise_routine(&patient), ExerciseRoutine::new(&env));
+}
+
+#[test]
+fn test_transfer_patient_requires_every_party() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(TrainerPatientContract, (Address::generate(&env),));
+    let client = TrainerPatientContractClient::new(&env, &contract_id);
+
+    let from = Address::generate(&env);
+    let to = Address::generate(&env);
+    client.register_trainer(&from);
+    client.register_trainer(&to);
+    let (patient, patient_id) = enroll_patient(&env, &client, &from);
+
+    // The new trainer has not signed
+    let args: soroban_sdk::Vec<soroban_sdk::Val> = (from.clone(), to.clone(), patient.clone(), true).into_val(&env);
+    let invoke = MockAuthInvoke {
+        contract: &contract_id,
+        fn_name: "transfer_patient",
+        args: args.clone(),
+        sub_invokes: &[],
+    };
+    let result = client
+        .mock_auths(&[MockAuth { address: &from, invoke: &invoke }, MockAuth { address: &patient, invoke: &invoke }])
+        .try_transfer_patient(&from, &to, &patient, &true);
+    assert!(result.is_err());
+
+    // Nor has the patient
+    let result = client
+        .mock_auths(&[MockAuth { address: &from, invoke: &invoke }, MockAuth { address: &to, invoke: &invoke }])
+        .try_transfer_patient(&from, &to, &patient, &true);
+    assert!(result.is_err());
+
+    env.mock_all_auths();
+    assert_eq!(trainer_patients(&env, &contract_id, &client, &from), vec![&env, patient_id]);
+    assert!(trainer_patients(&env, &contract_id, &client, &to).is_empty());
+}
+
+// A contract with an exercise trainer, a nutritionist and an observer on one patient's care team
+fn setup_care_team(env: &Env) -> (TrainerPatientContractClient<'_>, Address, BytesN<32>, Address, Address, Address) {
+    env.mock_all_auths();
+    let client = TrainerPatientContractClient::new(env, &env.register(TrainerPatientContract, (Address::generate(env),)));
+
+    let coach = Address::generate(env);
+    let nutritionist = Address::generate(env);
+    let observer = Address::generate(env);
+    client.register_trainer(&coach);
+    client.register_trainer(&nutritionist);
+    client.register_trainer(&observer);
+    let (patient, patient_id) = enroll_patient(env, &client, &coach);
+    client.add_patient(&nutritionist, &patient, &CareRole::Nutrition);
+    client.add_patient(&observer, &patient, &CareRole::Observer);
+    (client, patient, patient_id, coach, nutritionist, observer)
+}
+
+#[test]
+fn test_patient_stored_before_care_teams() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let client = TrainerPatientContractClient::new(&env, &env.register(TrainerPatientContract, (Address::generate(&env),)));
+    let trainer = Address::generate(&env);
+    let patient = Address::generate(&env);
+    client.register_trainer(&trainer);
+    let trainer_id = client.get_patient_id(&trainer);
+    let patient_id = client.get_patient_id(&patient);
+
+    // Write the patient as the single-trainer release did
+    env.as_contract(&client.address, || {
+        let patient_key = (soroban_sdk::symbol_short!("PATIENT"), patient_id.clone());
+        env.storage().persistent().set(&patient_key, &LegacyPatientInfo { trainer: trainer_id.clone() });
+        crate::storage::put_trainer(&env, &trainer_id, &TrainerInfo { patients: vec![&env, patient_id.clone()] });
+        crate::storage::put_exercise_routine(&env, &patient_id, &ExerciseRoutine::new(&env));
+        crate::storage::put_meal_plan(&env, &patient_id, &MealPlan::new(&env));
+        crate::storage::put_progress(&env, &patient_id, &ProgressUpdate::new(&env));
+    });
+
+    // The trainer is read back as the team's exercise provider
+    assert_eq!(client.get_care_team(&patient), Map::from_array(&env, [(trainer_id.clone(), CareRole::Exercise)]));
+    client.update_exercise_routine(&trainer, &patient_id, &named_routine(&env, "Knee rehab"));
+    assert_eq!(client.get_all_data(&patient).exercise_routine.name, String::from_str(&env, "Knee rehab"));
+
+    // The next change to the team stores it in the current layout
+    let nutritionist = Address::generate(&env);
+    client.register_trainer(&nutritionist);
+    client.add_patient(&nutritionist, &patient, &CareRole::Nutrition);
+    env.as_contract(&client.address, || {
+        let patient_key = (soroban_sdk::symbol_short!("PATIENT"), patient_id.clone());
+        let stored: PatientInfo = env.storage().persistent().get(&patient_key).unwrap();
+        assert_eq!(stored.team.get(trainer_id.clone()), Some(CareRole::Exercise));
+        assert_eq!(stored.team.len(), 2);
+    });
+}
+
+#[test]
+fn test_care_team_roles_scope_updates() {
+    let env = Env::default();
+    let (client, patient, patient_id, coach, nutritionist, observer) = setup_care_team(&env);
+
+    let team = client.get_care_team(&patient);
+    assert_eq!(team.len(), 3);
+    assert_eq!(team.get(client.get_patient_id(&nutritionist)), Some(CareRole::Nutrition));
+    assert_eq!(
+        client.try_add_patient(&nutritionist, &patient, &CareRole::Exercise),
+        Err(Ok(ContractError::PatientAlreadyLinked)),
+        "A provider holds one role per patient"
+    );
+
+    let routine = ExerciseRoutine::new(&env);
+    let plan = MealPlan::new(&env);
+    let progress = ProgressUpdate::new(&env);
+    assert!(client.try_update_exercise_routine(&coach, &patient_id, &routine).is_ok());
+    assert_eq!(client.try_update_meal_plan(&coach, &patient_id, &plan), Err(Ok(ContractError::RoleNotPermitted)));
+    assert!(client.try_update_meal_plan(&nutritionist, &patient_id, &plan).is_ok());
+    let result = client.try_update_exercise_routine(&nutritionist, &patient_id, &routine);
+    assert_eq!(result, Err(Ok(ContractError::RoleNotPermitted)));
+    assert!(client.try_update_progress(&coach, &patient_id, &progress).is_ok());
+    assert!(client.try_update_progress(&nutritionist, &patient_id, &progress).is_ok());
+    assert_eq!(client.try_update_progress(&observer, &patient_id, &progress), Err(Ok(ContractError::RoleNotPermitted)));
+
+    // Every provider on the team can read the patient's data, and nobody else can
+    assert_eq!(client.get_patient_data(&observer, &patient_id), client.get_all_data(&patient));
+    let stranger = Address::generate(&env);
+    client.register_trainer(&stranger);
+    let result = client.try_get_patient_data(&stranger, &patient_id);
+    assert_eq!(result, Err(Ok(ContractError::PatientNotLinkedWithTrainer)));
+}
+
+#[test]
+fn test_open_invitations_from_several_trainers() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let client = TrainerPatientContractClient::new(&env, &env.register(TrainerPatientContract, (Address::generate(&env),)));
+
+    let coach = Address::generate(&env);
+    let nutritionist = Address::generate(&env);
+    let patient = Address::generate(&env);
+    client.register_trainer(&coach);
+    client.register_trainer(&nutritionist);
+
+    client.invite_patient(&coach, &patient, &CareRole::Exercise);
+    client.invite_patient(&nutritionist, &patient, &CareRole::Nutrition);
+    client.accept_invitation(&patient, &nutritionist);
+    assert_eq!(client.get_invitation(&patient, &coach), Some(CareRole::Exercise), "Other invitations stay open");
+    client.accept_invitation(&patient, &coach);
+
+    let team = client.get_care_team(&patient);
+    assert_eq!(team.get(client.get_patient_id(&coach)), Some(CareRole::Exercise));
+    assert_eq!(team.get(client.get_patient_id(&nutritionist)), Some(CareRole::Nutrition));
+}
+
+#[test]
+fn test_records_kept_when_providers_leave() {
+    let env = Env::default();
+    let (client, patient, patient_id, coach, nutritionist, observer) = setup_care_team(&env);
+    let mut plan = MealPlan::new(&env);
+    plan.name = String::from_str(&env, "Low sodium");
+    client.update_meal_plan(&nutritionist, &patient_id, &plan);
+
+    client.remove_patient(&coach, &patient_id);
+    client.leave_trainer(&patient, &observer);
+    assert_eq!(client.get_care_team(&patient).len(), 1);
+    assert_eq!(client.get_meal_plan(&patient).name, plan.name);
+    let result = client.try_update_progress(&coach, &patient_id, &ProgressUpdate::new(&env));
+    assert_eq!(result, Err(Ok(ContractError::PatientNotLinkedWithTrainer)));
+
+    // A single trainer's signature cannot wipe the patient's records
+    client.leave_trainer(&patient, &nutritionist);
+    assert!(client.get_care_team(&patient).is_empty());
+    assert_eq!(client.get_meal_plan(&patient).name, plan.name);
+    assert_eq!(client.list_meal_plan_history(&patient, &0, &10).plans.len(), 1);
+}
+
+#[test]
+fn test_transfer_resets_only_the_role_records() {
+    let env = Env::default();
+    let (client, patient, patient_id, coach, nutritionist, _observer) = setup_care_team(&env);
+    let mut routine = ExerciseRoutine::new(&env);
+    routine.name = String::from_str(&env, "Knee rehab");
+    client.update_exercise_routine(&coach, &patient_id, &routine);
+    let mut plan = MealPlan::new(&env);
+    plan.name = String::from_str(&env, "Low sodium");
+    client.update_meal_plan(&nutritionist, &patient_id, &plan);
+    let mut progress = ProgressUpdate::new(&env);
+    progress.notes = String::from_str(&env, "Walking unaided");
+    client.update_progress(&coach, &patient_id, &progress);
+
+    let new_coach = Address::generate(&env);
+    client.register_trainer(&new_coach);
+    assert_eq!(
+        client.try_transfer_patient(&coach, &nutritionist, &patient, &false),
+        Err(Ok(ContractError::PatientAlreadyLinked))
+    );
+    client.transfer_patient(&coach, &new_coach, &patient, &false);
+
+    // The routine is reset; the nutritionist still works on the meal plan and progress
+    let data = client.get_all_data(&patient);
+    assert_eq!(data.exercise_routine, ExerciseRoutine::new(&env));
+    assert_eq!(data.meal_plan.name, plan.name);
+    assert_eq!(data.progress.notes, progress.notes);
+    assert_eq!(client.get_care_team(&patient).get(client.get_patient_id(&new_coach)), Some(CareRole::Exercise));
+    assert!(client.try_update_exercise_routine(&new_coach, &patient_id, &routine).is_ok());
+}
+
+// A routine named `name`, for telling versions apart
+fn named_routine(env: &Env, name: &str) -> ExerciseRoutine {
+    let mut routine = ExerciseRoutine::new(env);
+    routine.name = String::from_str(env, name);
+    routine
+}
+
+#[test]
+fn test_routine_history_keeps_every_version() {
+    let env = Env::default();
+    let (client, patient, patient_id, coach, _nutritionist, _observer) = setup_care_team(&env);
+    assert_eq!(client.list_routine_history(&patient, &0, &10), RoutinePage { routines: vec![&env], next_cursor: None });
+
+    for name in ["Week 1", "Week 2", "Week 3"] {
+        advance_days(&env, 7);
+        client.update_exercise_routine(&coach, &patient_id, &named_routine(&env, name));
+    }
+
+    assert_eq!(client.get_routine_version(&patient, &0).name, String::from_str(&env, "Week 1"));
+    assert_eq!(client.get_routine_version(&patient, &2), client.get_exercise_routine(&patient));
+    assert_eq!(client.try_get_routine_version(&patient, &3), Err(Ok(ContractError::VersionNotFound)));
+
+    let first = client.list_routine_history(&patient, &0, &2);
+    assert_eq!(first.routines.len(), 2);
+    assert_eq!(first.routines.get(1).unwrap().name, String::from_str(&env, "Week 2"));
+    assert_eq!(first.next_cursor, Some(2));
+    let last = client.list_routine_history(&patient, &2, &2);
+    assert_eq!(last.routines, vec![&env, client.get_exercise_routine(&patient)]);
+    assert_eq!(last.next_cursor, None);
+    assert_eq!(client.list_routine_history(&patient, &5, &2).routines.len(), 0);
+
+    assert_eq!(client.try_list_routine_history(&patient, &0, &0), Err(Ok(ContractError::InvalidDataFormat)));
+    assert_eq!(client.try_list_routine_history(&patient, &0, &101), Err(Ok(ContractError::InvalidDataFormat)));
+    let stranger = Address::generate(&env);
+    assert_eq!(client.try_get_routine_version(&stranger, &0), Err(Ok(ContractError::NotPatient)));
+}
+
+#[test]
+fn test_progress_is_kept_as_a_time_series() {
+    let env = Env::default();
+    let (client, patient, patient_id, coach, nutritionist, _observer) = setup_care_team(&env);
+
+    let mut plan = MealPlan::new(&env);
+    plan.name = String::from_str(&env, "Low sodium");
+    client.update_meal_plan(&nutritionist, &patient_id, &plan);
+    plan.name = String::from_str(&env, "Mediterranean");
+    client.update_meal_plan(&nutritionist, &patient_id, &plan);
+    assert_eq!(client.get_meal_plan_version(&patient, &0).name, String::from_str(&env, "Low sodium"));
+    assert_eq!(client.list_meal_plan_history(&patient, &0, &10).plans.len(), 2);
+
+    // Progress from every provider is kept, in the order it was recorded
+    let mut weights = std::vec::Vec::new();
+    for (trainer, weight) in [(&coach, "82kg"), (&nutritionist, "81kg"), (&coach, "79kg")] {
+        advance_days(&env, 1);
+        let mut progress = ProgressUpdate::new(&env);
+        progress.date = env.ledger().timestamp();
+        progress.metrics.set(String::from_str(&env, "weight"), String::from_str(&env, weight));
+        client.update_progress(trainer, &patient_id, &progress);
+        weights.push(String::from_str(&env, weight));
+    }
+
+    let series = client.list_progress_history(&patient, &0, &10);
+    assert_eq!(series.next_cursor, None);
+    assert_eq!(series.updates.len(), 3);
+    let mut last_date = 0;
+    for (update, weight) in series.updates.iter().zip(weights) {
+        assert!(update.date > last_date, "Updates should be in time order");
+        last_date = update.date;
+        assert_eq!(update.metrics.get(String::from_str(&env, "weight")), Some(weight));
+    }
+    assert_eq!(series.updates.last().unwrap(), client.get_progress(&patient));
+}
+
+#[test]
+fn test_history_resets_with_the_records() {
+    let env = Env::default();
+    let (client, patient, patient_id, coach, nutritionist, observer) = setup_care_team(&env);
+    client.update_exercise_routine(&coach, &patient_id, &named_routine(&env, "Knee rehab"));
+    client.update_meal_plan(&nutritionist, &patient_id, &MealPlan::new(&env));
+
+    // A handoff without history drops the versions the role covered and keeps the rest
+    let new_coach = Address::generate(&env);
+    client.register_trainer(&new_coach);
+    client.transfer_patient(&coach, &new_coach, &patient, &false);
+    assert_eq!(client.list_routine_history(&patient, &0, &10).routines.len(), 0);
+    assert_eq!(client.list_meal_plan_history(&patient, &0, &10).plans.len(), 1);
+    let version_key = (soroban_sdk::symbol_short!("RTN_HIST"), patient_id.clone(), 0u32);
+    env.as_contract(&client.address, || {
+        assert!(env.storage().persistent().has(&version_key), "Dropped versions should be left to expire");
+    });
+    client.update_exercise_routine(&new_coach, &patient_id, &named_routine(&env, "Strength"));
+    assert_eq!(client.get_routine_version(&patient, &0).name, String::from_str(&env, "Strength"));
+
+    // A patient who leaves every provider and returns still has their history
+    for trainer in [&new_coach, &nutritionist, &observer] {
+        client.leave_trainer(&patient, trainer);
+    }
+    client.add_patient(&coach, &patient, &CareRole::Exercise);
+    assert_eq!(client.get_routine_version(&patient, &0).name, String::from_str(&env, "Strength"));
+    assert_eq!(client.list_meal_plan_history(&patient, &0, &10).plans.len(), 1);
+}
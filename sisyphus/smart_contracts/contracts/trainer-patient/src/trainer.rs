@@ -1,77 +1,258 @@
-use soroban_sdk::{Address, BytesN, Env, Vec};
+use soroban_sdk::{Address, BytesN, Env, Map, Vec};
+use crate::subscription::Entitlements;
 use crate::error::ContractError;
-use crate::types::{TrainerInfo, PatientInfo, ExerciseRoutine, MealPlan, ProgressUpdate};
+use crate::types::{CareRole, TrainerInfo, PatientInfo, PatientData, ExerciseRoutine, MealPlan, ProgressUpdate};
 use crate::storage;
 use crate::access::AccessControl;
+use crate::patient::PatientInterface;
+use crate::history::{self, History};
 
 pub struct TrainerInterface;
 
 impl TrainerInterface {
     // Register a new trainer
     pub fn register(env: &Env, trainer_address: &Address) -> Result<(), ContractError> {
-        // Get trainer ID
-        let trainer_id = storage::get_trainer_id_from_address(env, trainer_address)
-            .ok_or(ContractError::InternalError)?;
-        
+        // Only subscribed trainers may register
+        AccessControl::require_subscription(env, trainer_address)?;
+
+        // Authenticate the trainer and get their ID
+        let trainer_id = AccessControl::authenticate(env, trainer_address)?;
+
         // Check if the trainer is already registered
         if storage::has_trainer(env, &trainer_id) {
             return Err(ContractError::TrainerAlreadyRegistered);
         }
-        
+
         // Create new trainer info
         let trainer_info = TrainerInfo {
             patients: Vec::new(env),
         };
-        
+
         // Store trainer info
         storage::put_trainer(env, &trainer_id, &trainer_info);
-        
+
         Ok(())
     }
 
-    // Add a patient to a trainer's list
+    // Add a patient to the trainer's care in `role`. The patient signs the same
+    // transaction as the trainer to consent.
     pub fn add_patient(
         env: &Env,
         trainer_address: &Address,
-        _patient_address: &Address,
-        patient_id: &BytesN<32>,
+        patient_address: &Address,
+        role: CareRole,
     ) -> Result<(), ContractError> {
-        // Verify the trainer is registered
+        AccessControl::authenticate_trainer(env, trainer_address)?;
+        AccessControl::authenticate(env, patient_address)?;
+        Self::link_patient(env, trainer_address, patient_address, role)
+    }
+
+    // Invite a patient to the trainer's care in `role`; they join once they accept
+    pub fn invite_patient(
+        env: &Env,
+        trainer_address: &Address,
+        patient_address: &Address,
+        role: CareRole,
+    ) -> Result<(), ContractError> {
+        // Verify the trainer is registered, authorized the invitation and is subscribed
+        let trainer_id = AccessControl::authenticate_trainer(env, trainer_address)?;
+        AccessControl::require_subscription(env, trainer_address)?;
+
+        let patient_id = storage::get_patient_id_from_address(env, patient_address)
+            .ok_or(ContractError::InternalError)?;
+        if Self::care_team(env, &patient_id).contains_key(trainer_id.clone()) {
+            return Err(ContractError::PatientAlreadyLinked);
+        }
+
+        // A newer invitation from the same trainer replaces an earlier one
+        storage::put_invitation(env, &patient_id, &trainer_id, &role);
+
+        Ok(())
+    }
+
+    // Add a consenting patient to a trainer's care in `role`. The patient ID is derived from
+    // their address. Callers authenticate whoever must consent; the trainer need not sign an
+    // accepted invitation.
+    pub fn link_patient(
+        env: &Env,
+        trainer_address: &Address,
+        patient_address: &Address,
+        role: CareRole,
+    ) -> Result<(), ContractError> {
+        // Verify the trainer is registered and subscribed
         let trainer_id = AccessControl::require_trainer(env, trainer_address)?;
-        
+        let entitlements = AccessControl::require_subscription(env, trainer_address)?;
+        let patient_id = storage::get_patient_id_from_address(env, patient_address)
+            .ok_or(ContractError::InternalError)?;
+
         // Get trainer's information
         let mut trainer_info = storage::get_trainer(env, &trainer_id)
             .ok_or(ContractError::TrainerNotFound)?;
-        
-        // Check if the patient is already linked
-        if trainer_info.patients.contains(patient_id) {
-            return Err(ContractError::PatientAlreadyLinked);
-        }
-        
-        // Check if the patient is already linked to another trainer
-        if storage::has_patient(env, patient_id) {
+
+        // Check the trainer is not already on the patient's care team
+        let existing = storage::get_patient(env, &patient_id);
+        let mut team = existing.clone().map(|info| info.team).unwrap_or_else(|| Map::new(env));
+        if team.contains_key(trainer_id.clone()) {
             return Err(ContractError::PatientAlreadyLinked);
         }
-        
+
+        // Check the trainer's plan has room for another patient
+        Self::require_capacity(&entitlements, &trainer_info)?;
+
         // Add patient to trainer's list
         trainer_info.patients.push_back(patient_id.clone());
         storage::put_trainer(env, &trainer_id, &trainer_info);
-        
-        // Create patient info and link to trainer
-        let patient_info = PatientInfo {
-            trainer: trainer_id.clone(),
-        };
+
+        // Add the trainer to the patient's care team
+        team.set(trainer_id.clone(), role);
+        storage::put_patient(env, &patient_id, &PatientInfo { team });
+        storage::remove_invitation(env, &patient_id, &trainer_id);
+
+        // Initialize empty data structures for a new patient
+        if existing.is_none() {
+            let routine = ExerciseRoutine::new(env);
+            let meal_plan = MealPlan::new(env);
+            let progress = ProgressUpdate::new(env);
+
+            storage::put_exercise_routine(env, &patient_id, &routine);
+            storage::put_meal_plan(env, &patient_id, &meal_plan);
+            storage::put_progress(env, &patient_id, &progress);
+        }
+
+        Ok(())
+    }
+
+    // Take the authorizing trainer off a patient's care team
+    pub fn remove_patient(
+        env: &Env,
+        trainer_address: &Address,
+        patient_id: &BytesN<32>,
+    ) -> Result<(), ContractError> {
+        // Verify the trainer authorized the removal and is on the team
+        AccessControl::trainer_has_patient_access(env, trainer_address, patient_id)?;
+        let trainer_id = storage::get_trainer_id_from_address(env, trainer_address)
+            .ok_or(ContractError::InternalError)?;
+
+        Self::unlink_patient(env, patient_id, &trainer_id)
+    }
+
+    // Hand `from`'s place on a patient's care team to `to`, in the same role. Both trainers and
+    // the patient must authorize it. With `keep_history` the patient's records are kept;
+    // otherwise those the role covers and no other provider on the team works on are reset,
+    // along with their histories.
+    pub fn transfer_patient(
+        env: &Env,
+        from: &Address,
+        to: &Address,
+        patient_address: &Address,
+        keep_history: bool,
+    ) -> Result<(), ContractError> {
+        if from == to {
+            return Err(ContractError::PatientAlreadyLinked);
+        }
+
+        // Verify the current trainer authorized the handoff and is on the team
+        let patient_id = AccessControl::authenticate(env, patient_address)?;
+        let role = AccessControl::trainer_has_patient_access(env, from, &patient_id)?;
+        let from_id = storage::get_trainer_id_from_address(env, from)
+            .ok_or(ContractError::InternalError)?;
+
+        // Verify the new trainer authorized it, is subscribed and has room for the patient
+        let to_id = AccessControl::authenticate_trainer(env, to)?;
+        let mut team = Self::care_team(env, &patient_id);
+        if team.contains_key(to_id.clone()) {
+            return Err(ContractError::PatientAlreadyLinked);
+        }
+        let entitlements = AccessControl::require_subscription(env, to)?;
+        let mut to_info = storage::get_trainer(env, &to_id)
+            .ok_or(ContractError::TrainerNotFound)?;
+        Self::require_capacity(&entitlements, &to_info)?;
+
+        // Move the patient between the trainers' lists
+        let mut from_info = storage::get_trainer(env, &from_id)
+            .ok_or(ContractError::TrainerNotFound)?;
+        if let Some(index) = from_info.patients.first_index_of(&patient_id) {
+            from_info.patients.remove(index);
+        }
+        storage::put_trainer(env, &from_id, &from_info);
+        to_info.patients.push_back(patient_id.clone());
+        storage::put_trainer(env, &to_id, &to_info);
+
+        // Give the new trainer the old one's place on the team
+        team.remove(from_id);
+        if !keep_history {
+            let shared = |permits: fn(&CareRole) -> bool| team.values().iter().any(|other| permits(&other));
+            if role.can_update_routine() && !shared(CareRole::can_update_routine) {
+                storage::put_exercise_routine(env, &patient_id, &ExerciseRoutine::new(env));
+                storage::clear_history(env, History::Routine, &patient_id);
+            }
+            if role.can_update_meal_plan() && !shared(CareRole::can_update_meal_plan) {
+                storage::put_meal_plan(env, &patient_id, &MealPlan::new(env));
+                storage::clear_history(env, History::MealPlan, &patient_id);
+            }
+            if role.can_update_progress() && !shared(CareRole::can_update_progress) {
+                storage::put_progress(env, &patient_id, &ProgressUpdate::new(env));
+                storage::clear_history(env, History::Progress, &patient_id);
+            }
+        }
+        team.set(to_id, role);
+        storage::put_patient(env, &patient_id, &PatientInfo { team });
+
+        Ok(())
+    }
+
+    // Take a trainer off a patient's care team. The patient's records and histories are kept,
+    // even once the team is empty, and are there again if they rejoin a trainer. Callers
+    // authenticate whoever ends the link, the trainer or the patient.
+    pub fn unlink_patient(
+        env: &Env,
+        patient_id: &BytesN<32>,
+        trainer_id: &BytesN<32>,
+    ) -> Result<(), ContractError> {
+        let mut patient_info = storage::get_patient(env, patient_id)
+            .ok_or(ContractError::PatientNotFound)?;
+        if patient_info.team.remove(trainer_id.clone()).is_none() {
+            return Err(ContractError::PatientNotLinkedWithTrainer);
+        }
+
+        let mut trainer_info = storage::get_trainer(env, trainer_id)
+            .ok_or(ContractError::TrainerNotFound)?;
+        if let Some(index) = trainer_info.patients.first_index_of(patient_id) {
+            trainer_info.patients.remove(index);
+        }
+        storage::put_trainer(env, trainer_id, &trainer_info);
         storage::put_patient(env, patient_id, &patient_info);
-        
-        // Initialize empty data structures for the patient
-        let routine = ExerciseRoutine::new(env);
-        let meal_plan = MealPlan::new(env);
-        let progress = ProgressUpdate::new(env);
-        
-        storage::put_exercise_routine(env, patient_id, &routine);
-        storage::put_meal_plan(env, patient_id, &meal_plan);
-        storage::put_progress(env, patient_id, &progress);
-        
+
+        Ok(())
+    }
+
+    // Read the data of a patient on the authorizing trainer's care, whatever their role
+    pub fn get_patient_data(
+        env: &Env,
+        trainer_address: &Address,
+        patient_id: &BytesN<32>,
+    ) -> Result<PatientData, ContractError> {
+        AccessControl::trainer_has_patient_access(env, trainer_address, patient_id)?;
+        Ok(PatientInterface::patient_data(env, patient_id))
+    }
+
+    // A patient's care team, empty if they have none
+    fn care_team(env: &Env, patient_id: &BytesN<32>) -> Map<BytesN<32>, CareRole> {
+        storage::get_patient(env, patient_id)
+            .map(|info| info.team)
+            .unwrap_or_else(|| Map::new(env))
+    }
+
+    // Check a trainer's plan has room for another patient
+    fn require_capacity(
+        entitlements: &Option<Entitlements>,
+        trainer_info: &TrainerInfo,
+    ) -> Result<(), ContractError> {
+        if let Some(entitlements) = entitlements {
+            if entitlements.max_patients != 0 && trainer_info.patients.len() >= entitlements.max_patients {
+                return Err(ContractError::PatientLimitReached);
+            }
+        }
         Ok(())
     }
 
@@ -82,15 +263,17 @@ impl TrainerInterface {
         patient_id: &BytesN<32>,
         routine: &ExerciseRoutine,
     ) -> Result<(), ContractError> {
-        // Verify the trainer has access to this patient
-        AccessControl::trainer_has_patient_access(env, trainer_address, patient_id)?;
+        // Verify the trainer authorized the update, may edit this patient's routine and is subscribed
+        AccessControl::require_role(env, trainer_address, patient_id, CareRole::can_update_routine)?;
+        AccessControl::require_subscription(env, trainer_address)?;
         
         // Update the routine with current timestamp
         let mut updated_routine = routine.clone();
         updated_routine.last_updated = env.ledger().timestamp();
         
-        // Store the updated routine
+        // Store the updated routine and keep it as a new version
         storage::put_exercise_routine(env, patient_id, &updated_routine);
+        history::record(env, History::Routine, patient_id, &updated_routine);
         
         Ok(())
     }
@@ -102,15 +285,17 @@ impl TrainerInterface {
         patient_id: &BytesN<32>,
         plan: &MealPlan,
     ) -> Result<(), ContractError> {
-        // Verify the trainer has access to this patient
-        AccessControl::trainer_has_patient_access(env, trainer_address, patient_id)?;
+        // Verify the trainer authorized the update, may edit this patient's meal plan and is subscribed
+        AccessControl::require_role(env, trainer_address, patient_id, CareRole::can_update_meal_plan)?;
+        AccessControl::require_subscription(env, trainer_address)?;
         
         // Update the meal plan with current timestamp
         let mut updated_plan = plan.clone();
         updated_plan.last_updated = env.ledger().timestamp();
         
-        // Store the updated meal plan
+        // Store the updated meal plan and keep it as a new version
         storage::put_meal_plan(env, patient_id, &updated_plan);
+        history::record(env, History::MealPlan, patient_id, &updated_plan);
         
         Ok(())
     }
@@ -122,15 +307,17 @@ impl TrainerInterface {
         patient_id: &BytesN<32>,
         progress: &ProgressUpdate,
     ) -> Result<(), ContractError> {
-        // Verify the trainer has access to this patient
-        AccessControl::trainer_has_patient_access(env, trainer_address, patient_id)?;
+        // Verify the trainer authorized the update, may record this patient's progress and is subscribed
+        AccessControl::require_role(env, trainer_address, patient_id, CareRole::can_update_progress)?;
+        AccessControl::require_subscription(env, trainer_address)?;
         
         // Update the progress with current timestamp
         let mut updated_progress = progress.clone();
         updated_progress.last_updated = env.ledger().timestamp();
         
-        // Store the updated progress
+        // Store the updated progress and add it to the patient's progress over time
         storage::put_progress(env, patient_id, &updated_progress);
+        history::record(env, History::Progress, patient_id, &updated_progress);
         
         Ok(())
     }
@@ -0,0 +1,33 @@
+use soroban_sdk::{Env, IntoVal, Val};
+use storage_ttl::Policy;
+use crate::error::ContractError;
+use crate::storage;
+use crate::types::TtlPolicy;
+
+// The configured TTL policy, or the default
+pub fn policy(env: &Env) -> TtlPolicy {
+    let policy = current(env);
+    TtlPolicy { threshold: policy.threshold, extend_to: policy.extend_to }
+}
+
+pub fn current(env: &Env) -> Policy {
+    storage::get_ttl_policy(env)
+        .map(|policy| Policy { threshold: policy.threshold, extend_to: policy.extend_to })
+        .unwrap_or_default()
+}
+
+pub fn validate(env: &Env, policy: &TtlPolicy) -> Result<(), ContractError> {
+    if !(Policy { threshold: policy.threshold, extend_to: policy.extend_to }).is_valid(env) {
+        return Err(ContractError::InvalidTtlPolicy);
+    }
+    Ok(())
+}
+
+// The instance holds the admin and configuration
+pub fn extend_instance(env: &Env) {
+    current(env).extend_instance(env);
+}
+
+pub fn extend<K: IntoVal<Env, Val>>(env: &Env, key: &K) -> bool {
+    current(env).extend(env, key)
+}
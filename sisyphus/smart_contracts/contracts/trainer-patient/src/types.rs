@@ -58,6 +58,29 @@ pub struct PatientData {
     pub progress: ProgressUpdate,
 }
 
+// Pages of a patient's record histories, oldest first. `next_cursor` is None once the history
+// is exhausted.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RoutinePage {
+    pub routines: Vec<ExerciseRoutine>,
+    pub next_cursor: Option<u32>,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MealPlanPage {
+    pub plans: Vec<MealPlan>,
+    pub next_cursor: Option<u32>,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProgressPage {
+    pub updates: Vec<ProgressUpdate>,    // The patient's progress over time
+    pub next_cursor: Option<u32>,
+}
+
 // Trainer data structure
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -65,13 +88,44 @@ pub struct TrainerInfo {
     pub patients: Vec<BytesN<32>>,
 }
 
+// What a provider on a patient's care team may update
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CareRole {
+    Exercise,     // The exercise routine and progress
+    Nutrition,    // The meal plan and progress
+    Observer,     // Nothing; can only read the patient's data
+}
+
 // Patient data structure
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct PatientInfo {
+    pub team: Map<BytesN<32>, CareRole>,    // Role of each provider on the care team, by trainer ID
+}
+
+// Patient data as stored before care teams, naming the patient's one trainer; only read by
+// `storage::get_patient`, which converts it
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LegacyPatientInfo {
     pub trainer: BytesN<32>,
 }
 
+impl CareRole {
+    pub fn can_update_routine(&self) -> bool {
+        *self == CareRole::Exercise
+    }
+
+    pub fn can_update_meal_plan(&self) -> bool {
+        *self == CareRole::Nutrition
+    }
+
+    pub fn can_update_progress(&self) -> bool {
+        *self != CareRole::Observer
+    }
+}
+
 // Default implementations for new objects
 impl ExerciseRoutine {
     pub fn new(env: &Env) -> Self {
@@ -114,4 +168,12 @@ impl PatientData {
             progress: ProgressUpdate::new(env),
         }
     }
-} 
\ No newline at end of file
+}
+
+// How long storage entries are kept live, in ledgers
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TtlPolicy {
+    pub threshold: u32,    // Entries with fewer ledgers left than this are extended on access
+    pub extend_to: u32,    // Ledgers an extended entry is kept live for
+}
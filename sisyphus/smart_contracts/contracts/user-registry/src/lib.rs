@@ -1,9 +1,11 @@
 #![no_std]
 use soroban_sdk::{
     contract, contractimpl, contracttype, 
-    Address, Env, Map, String, Vec,
+    Address, BytesN, Env, Map, String, Vec,
 };
 
+mod ttl;
+
 /// User data structure to store on the blockchain
 #[contracttype]
 #[derive(Clone)]
@@ -13,21 +15,223 @@ pub struct UserData {
     pub metadata: Map<String, String>,
 }
 
+/// How long storage entries are kept live, in ledgers
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TtlPolicy {
+    /// Entries with fewer ledgers left than this are extended on access
+    pub threshold: u32,
+    /// Ledgers an extended entry is kept live for
+    pub extend_to: u32,
+}
+
 /// Main contract for user registry management
 #[contract]
 pub struct UserRegistry;
 
 /// DataKey enum to define storage keys
 #[contracttype]
+#[derive(Clone)]
 pub enum DataKey {
     /// Key for storing all registered user IDs
     UserIds,
     /// Key for storing user data, combined with the user's unique identifier
     UserData(Address),
+    /// Key for storing the contract administrator
+    Admin,
+    /// Key for storing the address the admin role has been offered to
+    PendingAdmin,
+    /// Key for storing whether user mutations are paused
+    Paused,
+    /// Key for storing the TTL policy, if the admin has set one
+    TtlPolicy,
 }
 
 #[contractimpl]
 impl UserRegistry {
+    /// Set the contract administrator. Runs once, as part of the deployment, so
+    /// nobody can claim the role between deploying and configuring the contract.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - The contract environment
+    /// * `admin` - The address that manages the contract
+    pub fn __constructor(env: Env, admin: Address) {
+        env.storage().instance().set(&DataKey::Admin, &admin);
+    }
+
+    /// Offer the admin role to a new address. The current admin keeps the role
+    /// until the new address calls `accept_admin`.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - The contract environment
+    /// * `new_admin` - The address to hand the role to
+    ///
+    /// # Returns
+    ///
+    /// Boolean indicating success, or false if no admin is set
+    pub fn transfer_admin(env: Env, new_admin: Address) -> bool {
+        let Some(admin) = Self::get_admin(env.clone()) else {
+            return false;
+        };
+
+        // Only the current admin can hand over the role
+        admin.require_auth();
+        env.storage().instance().set(&DataKey::PendingAdmin, &new_admin);
+
+        true
+    }
+
+    /// Take over the admin role offered by `transfer_admin`
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - The contract environment
+    ///
+    /// # Returns
+    ///
+    /// Boolean indicating success, or false if no handover is in progress
+    pub fn accept_admin(env: Env) -> bool {
+        let Some(new_admin) = env.storage().instance().get::<DataKey, Address>(&DataKey::PendingAdmin) else {
+            return false;
+        };
+
+        // The new admin must control the address it was offered to
+        new_admin.require_auth();
+        env.storage().instance().set(&DataKey::Admin, &new_admin);
+        env.storage().instance().remove(&DataKey::PendingAdmin);
+
+        true
+    }
+
+    /// Get the contract administrator
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - The contract environment
+    ///
+    /// # Returns
+    ///
+    /// The admin address, or None if no admin is set
+    pub fn get_admin(env: Env) -> Option<Address> {
+        env.storage().instance().get(&DataKey::Admin)
+    }
+
+    /// Stop or restart user mutations. While paused, `create_user`, `update_user`
+    /// and `delete_user` return false; reads keep working.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - The contract environment
+    /// * `paused` - Whether user mutations are blocked
+    ///
+    /// # Returns
+    ///
+    /// Boolean indicating success, or false if no admin is set
+    pub fn set_paused(env: Env, paused: bool) -> bool {
+        let Some(admin) = Self::get_admin(env.clone()) else {
+            return false;
+        };
+
+        admin.require_auth();
+        env.storage().instance().set(&DataKey::Paused, &paused);
+
+        true
+    }
+
+    /// Check whether user mutations are paused
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - The contract environment
+    ///
+    /// # Returns
+    ///
+    /// Boolean indicating if the contract is paused
+    pub fn is_paused(env: Env) -> bool {
+        env.storage().instance().get(&DataKey::Paused).unwrap_or(false)
+    }
+
+    /// Replace the contract code with a WASM already uploaded to the network.
+    /// Stored users are kept, so the new code must read the current layout.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - The contract environment
+    /// * `new_wasm_hash` - Hash of the uploaded WASM
+    ///
+    /// # Returns
+    ///
+    /// Boolean indicating success, or false if no admin is set
+    pub fn upgrade(env: Env, new_wasm_hash: BytesN<32>) -> bool {
+        let Some(admin) = Self::get_admin(env.clone()) else {
+            return false;
+        };
+
+        admin.require_auth();
+        env.deployer().update_current_contract_wasm(new_wasm_hash);
+
+        true
+    }
+
+    /// Set how long storage entries are kept live: entries with fewer than
+    /// `threshold` ledgers left are extended to `extend_to` whenever they are
+    /// read or written
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - The contract environment
+    /// * `threshold` - Ledgers left below which an entry is extended
+    /// * `extend_to` - Ledgers an extended entry is kept live for
+    ///
+    /// # Returns
+    ///
+    /// Boolean indicating success, or false if no admin is set or
+    /// `threshold` is not between 0 and `extend_to`, or `extend_to` is above the network maximum
+    pub fn set_ttl_policy(env: Env, threshold: u32, extend_to: u32) -> bool {
+        let Some(admin) = Self::get_admin(env.clone()) else {
+            return false;
+        };
+        let policy = TtlPolicy { threshold, extend_to };
+        if !ttl::is_valid(&env, &policy) {
+            return false;
+        }
+
+        admin.require_auth();
+        env.storage().instance().set(&DataKey::TtlPolicy, &policy);
+        ttl::extend_instance(&env);
+
+        true
+    }
+
+    /// Get the TTL policy in force
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - The contract environment
+    ///
+    /// # Returns
+    ///
+    /// The policy the admin set, or the default of 15 days extended to 30
+    pub fn get_ttl_policy(env: Env) -> TtlPolicy {
+        ttl::policy(&env)
+    }
+
+    /// Extend the contract instance and the given entries, so users nobody has
+    /// read lately are not archived. Anyone may call this.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - The contract environment
+    /// * `keys` - Storage keys to extend; keys with no entry are skipped
+    pub fn bump(env: Env, keys: Vec<DataKey>) {
+        ttl::extend_instance(&env);
+        for key in keys.iter() {
+            ttl::extend(&env, &key);
+        }
+    }
+
     /// Create a new user with the provided data
     /// 
     /// # Arguments
@@ -40,7 +244,7 @@ impl UserRegistry {
     ///
     /// # Returns
     ///
-    /// Boolean indicating success or failure
+    /// Boolean indicating success or failure; false while the contract is paused
     pub fn create_user(
         env: Env,
         user_id: Address,
@@ -48,8 +252,8 @@ impl UserRegistry {
         email: String,
         metadata: Map<String, String>,
     ) -> bool {
-        // Verify the user is not already registered
-        if Self::user_exists(&env, &user_id) {
+        // Refuse while paused, and verify the user is not already registered
+        if Self::is_paused(env.clone()) || Self::user_exists(&env, &user_id) {
             return false;
         }
 
@@ -64,12 +268,12 @@ impl UserRegistry {
         };
 
         // Store the user data
-        env.storage().persistent().set(&DataKey::UserData(user_id.clone()), &user_data);
+        ttl::set(&env, &DataKey::UserData(user_id.clone()), &user_data);
 
         // Add user ID to the list of registered users
         let mut user_ids = Self::get_all_user_ids(&env);
         user_ids.push_back(user_id);
-        env.storage().persistent().set(&DataKey::UserIds, &user_ids);
+        ttl::set(&env, &DataKey::UserIds, &user_ids);
 
         true
     }
@@ -86,7 +290,7 @@ impl UserRegistry {
     ///
     /// # Returns
     ///
-    /// Boolean indicating success or failure
+    /// Boolean indicating success or failure; false while the contract is paused
     pub fn update_user(
         env: Env,
         user_id: Address,
@@ -94,8 +298,8 @@ impl UserRegistry {
         email: String,
         metadata: Map<String, String>,
     ) -> bool {
-        // Verify the user exists
-        if !Self::user_exists(&env, &user_id) {
+        // Refuse while paused, and verify the user exists
+        if Self::is_paused(env.clone()) || !Self::user_exists(&env, &user_id) {
             return false;
         }
 
@@ -110,7 +314,7 @@ impl UserRegistry {
         };
 
         // Update the user data
-        env.storage().persistent().set(&DataKey::UserData(user_id), &user_data);
+        ttl::set(&env, &DataKey::UserData(user_id), &user_data);
 
         true
     }
@@ -127,7 +331,7 @@ impl UserRegistry {
     /// The user data if found, or None if not found
     pub fn get_user(env: Env, user_id: Address) -> Option<UserData> {
         if Self::user_exists(&env, &user_id) {
-            let user_data: UserData = env.storage().persistent().get(&DataKey::UserData(user_id)).unwrap();
+            let user_data: UserData = ttl::get(&env, &DataKey::UserData(user_id)).unwrap();
             Some(user_data)
         } else {
             None
@@ -144,7 +348,7 @@ impl UserRegistry {
     ///
     /// Vector containing all registered user IDs
     pub fn get_all_user_ids(env: &Env) -> Vec<Address> {
-        match env.storage().persistent().get::<DataKey, Vec<Address>>(&DataKey::UserIds) {
+        match ttl::get::<Vec<Address>>(env, &DataKey::UserIds) {
             Some(ids) => ids,
             None => Vec::new(env),
         }
@@ -161,7 +365,7 @@ impl UserRegistry {
     ///
     /// Boolean indicating if the user exists
     fn user_exists(env: &Env, user_id: &Address) -> bool {
-        env.storage().persistent().has(&DataKey::UserData(user_id.clone()))
+        ttl::has(env, &DataKey::UserData(user_id.clone()))
     }
 
     /// Delete a user from the registry
@@ -173,10 +377,10 @@ impl UserRegistry {
     ///
     /// # Returns
     ///
-    /// Boolean indicating success or failure
+    /// Boolean indicating success or failure; false while the contract is paused
     pub fn delete_user(env: Env, user_id: Address) -> bool {
-        // Verify the user exists
-        if !Self::user_exists(&env, &user_id) {
+        // Refuse while paused, and verify the user exists
+        if Self::is_paused(env.clone()) || !Self::user_exists(&env, &user_id) {
             return false;
         }
 
@@ -196,7 +400,7 @@ impl UserRegistry {
             }
         }
         
-        env.storage().persistent().set(&DataKey::UserIds, &new_ids);
+        ttl::set(&env, &DataKey::UserIds, &new_ids);
 
         true
     }
@@ -9,7 +9,7 @@ use soroban_sdk::{
 #[test]
 fn test_create_user() {
     let env = Env::default();
-    let contract_id = env.register(UserRegistry, ());
+    let contract_id = env.register(UserRegistry, (Address::generate(&env),));
     let client = UserRegistryClient::new(&env, &contract_id);
 
     // Create a test user ID
@@ -48,7 +48,7 @@ fn test_create_user() {
 #[test]
 fn test_get_user() {
     let env = Env::default();
-    let contract_id = env.register(UserRegistry, ());
+    let contract_id = env.register(UserRegistry, (Address::generate(&env),));
     let client = UserRegistryClient::new(&env, &contract_id);
 
     // Create a test user ID
@@ -86,7 +86,7 @@ fn test_get_user() {
 #[test]
 fn test_update_user() {
     let env = Env::default();
-    let contract_id = env.register(UserRegistry, ());
+    let contract_id = env.register(UserRegistry, (Address::generate(&env),));
     let client = UserRegistryClient::new(&env, &contract_id);
 
     // Create a test user ID
@@ -142,7 +142,7 @@ fn test_update_user() {
 #[test]
 fn test_delete_user() {
     let env = Env::default();
-    let contract_id = env.register(UserRegistry, ());
+    let contract_id = env.register(UserRegistry, (Address::generate(&env),));
     let client = UserRegistryClient::new(&env, &contract_id);
 
     // Create test user IDs
@@ -189,4 +189,140 @@ fn test_delete_user() {
     let non_existent_user = Address::generate(&env);
     let result = client.delete_user(&non_existent_user);
     assert!(!result);
-} 
\ No newline at end of file
+} 
+#[test]
+fn test_admin_handover() {
+    let env = Env::default();
+    let admin = Address::generate(&env);
+    let contract_id = env.register(UserRegistry, (admin.clone(),));
+    let client = UserRegistryClient::new(&env, &contract_id);
+
+    let new_admin = Address::generate(&env);
+    env.mock_all_auths();
+
+    // The admin is set when the contract is deployed
+    assert_eq!(client.get_admin(), Some(admin.clone()));
+
+    // No handover is in progress yet
+    assert!(!client.accept_admin());
+
+    // The old admin keeps the role until the new one accepts
+    assert!(client.transfer_admin(&new_admin));
+    assert_eq!(client.get_admin(), Some(admin.clone()));
+
+    assert!(client.accept_admin());
+    assert_eq!(env.auths()[0].0, new_admin);
+    assert_eq!(client.get_admin(), Some(new_admin.clone()));
+
+    // Admin calls now need the new admin's signature
+    assert!(client.set_paused(&true));
+    assert_eq!(env.auths()[0].0, new_admin);
+}
+
+#[test]
+fn test_pause_blocks_user_mutations() {
+    let env = Env::default();
+    let contract_id = env.register(UserRegistry, (Address::generate(&env),));
+    let client = UserRegistryClient::new(&env, &contract_id);
+
+    let user = Address::generate(&env);
+    let name = String::from_str(&env, "Jane Doe");
+    let email = String::from_str(&env, "jane.doe@example.com");
+    let metadata = Map::new(&env);
+    env.mock_all_auths();
+
+    assert!(client.create_user(&user, &name, &email, &metadata));
+
+    // While paused, every user mutation is refused
+    assert!(client.set_paused(&true));
+    assert!(client.is_paused());
+    assert!(!client.create_user(&Address::generate(&env), &name, &email, &metadata));
+    assert!(!client.update_user(&user, &name, &email, &metadata));
+    assert!(!client.delete_user(&user));
+
+    // Reads keep working
+    assert!(client.get_user(&user).is_some());
+
+    // Unpausing restores mutations
+    assert!(client.set_paused(&false));
+    assert!(client.delete_user(&user));
+}
+
+#[test]
+fn test_admin_calls_require_admin() {
+    let env = Env::default();
+    let contract_id = env.register(UserRegistry, (Address::generate(&env),));
+    let client = UserRegistryClient::new(&env, &contract_id);
+
+    env.mock_all_auths();
+
+    // Without the admin's signature the calls are rejected
+    env.set_auths(&[]);
+    assert!(client.try_set_paused(&true).is_err());
+    assert!(client.try_transfer_admin(&Address::generate(&env)).is_err());
+    assert!(client.try_upgrade(&soroban_sdk::BytesN::from_array(&env, &[0; 32])).is_err());
+    assert!(!client.is_paused());
+}
+
+/// Close `days` worth of ledgers
+fn advance_days(env: &Env, days: u32) {
+    use soroban_sdk::testutils::Ledger;
+    env.ledger().with_mut(|ledger| {
+        ledger.sequence_number += days * storage_ttl::DAY_IN_LEDGERS;
+        ledger.timestamp += days as u64 * 86_400;
+    });
+}
+
+/// Ledgers left before a persistent entry of the contract is archived
+fn persistent_ttl(env: &Env, contract_id: &Address, key: &DataKey) -> u32 {
+    use soroban_sdk::testutils::storage::Persistent;
+    env.as_contract(contract_id, || env.storage().persistent().get_ttl(key))
+}
+
+#[test]
+fn test_users_stay_live_while_read() {
+    let env = Env::default();
+    let contract_id = env.register(UserRegistry, (Address::generate(&env),));
+    let client = UserRegistryClient::new(&env, &contract_id);
+
+    let user = Address::generate(&env);
+    env.mock_all_auths();
+    client.create_user(&user, &String::from_str(&env, "Jane Doe"), &String::from_str(&env, "jane.doe@example.com"), &Map::new(&env));
+    let key = DataKey::UserData(user.clone());
+    assert_eq!(persistent_ttl(&env, &contract_id, &key), storage_ttl::DEFAULT_EXTEND_TO);
+
+    // Each read past the threshold extends the user, well beyond the first 30 days
+    for _ in 0..3 {
+        advance_days(&env, 20);
+        assert!(client.get_user(&user).is_some());
+        assert_eq!(persistent_ttl(&env, &contract_id, &key), storage_ttl::DEFAULT_EXTEND_TO);
+    }
+}
+
+#[test]
+fn test_ttl_policy_and_bump() {
+    let env = Env::default();
+    let contract_id = env.register(UserRegistry, (Address::generate(&env),));
+    let client = UserRegistryClient::new(&env, &contract_id);
+
+    env.mock_all_auths();
+    assert!(!client.set_ttl_policy(&1_000, &1_000));
+    assert!(!client.set_ttl_policy(&0, &1_000));
+    assert!(client.set_ttl_policy(&(2 * storage_ttl::DAY_IN_LEDGERS), &(10 * storage_ttl::DAY_IN_LEDGERS)));
+    let policy = client.get_ttl_policy();
+    assert_eq!(policy.extend_to, 10 * storage_ttl::DAY_IN_LEDGERS);
+
+    let user = Address::generate(&env);
+    client.create_user(&user, &String::from_str(&env, "Jane Doe"), &String::from_str(&env, "jane.doe@example.com"), &Map::new(&env));
+
+    // Nobody reads the user, but anyone can keep it live
+    advance_days(&env, 9);
+    env.set_auths(&[]);
+    client.bump(&Vec::from_array(&env, [DataKey::UserData(user.clone()), DataKey::UserIds]));
+    assert!(env.auths().is_empty());
+    assert_eq!(persistent_ttl(&env, &contract_id, &DataKey::UserData(user.clone())), policy.extend_to);
+    assert_eq!(persistent_ttl(&env, &contract_id, &DataKey::UserIds), policy.extend_to);
+
+    advance_days(&env, 9);
+    assert!(client.get_user(&user).is_some());
+}
@@ -0,0 +1,42 @@
+use soroban_sdk::{Env, IntoVal, TryFromVal, Val};
+use storage_ttl::Policy;
+use crate::{DataKey, TtlPolicy};
+
+/// The configured TTL policy, or the default
+pub fn policy(env: &Env) -> TtlPolicy {
+    let policy = current(env);
+    TtlPolicy { threshold: policy.threshold, extend_to: policy.extend_to }
+}
+
+fn current(env: &Env) -> Policy {
+    env.storage()
+        .instance()
+        .get::<_, TtlPolicy>(&DataKey::TtlPolicy)
+        .map(|policy| Policy { threshold: policy.threshold, extend_to: policy.extend_to })
+        .unwrap_or_default()
+}
+
+pub fn is_valid(env: &Env, policy: &TtlPolicy) -> bool {
+    Policy { threshold: policy.threshold, extend_to: policy.extend_to }.is_valid(env)
+}
+
+/// The instance holds the admin
+pub fn extend_instance(env: &Env) {
+    current(env).extend_instance(env);
+}
+
+pub fn extend(env: &Env, key: &DataKey) -> bool {
+    current(env).extend(env, key)
+}
+
+pub fn has(env: &Env, key: &DataKey) -> bool {
+    current(env).has(env, key)
+}
+
+pub fn get<V: TryFromVal<Env, Val>>(env: &Env, key: &DataKey) -> Option<V> {
+    current(env).get(env, key)
+}
+
+pub fn set<V: IntoVal<Env, Val>>(env: &Env, key: &DataKey, value: &V) {
+    current(env).set(env, key, value);
+}
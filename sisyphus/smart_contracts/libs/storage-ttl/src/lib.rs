@@ -0,0 +1,74 @@
+//! Storage TTL handling shared by the contracts in this workspace.
+//!
+//! Persistent entries and the contract instance are archived once their TTL runs out. The
+//! contracts extend every entry they read or write, and the instance with it, so records in
+//! use are never archived; each also has a permissionless `bump` for records nobody has
+//! touched lately. An entry is only extended once it has fewer than `threshold` ledgers left,
+//! and then to `extend_to`, so frequent access does not pay for an extension every time. The
+//! admin can change both with the contract's `set_ttl_policy`; until then the default applies.
+#![no_std]
+
+use soroban_sdk::{Env, IntoVal, TryFromVal, Val};
+
+/// Ledgers closed in a day at the network's five-second close time
+pub const DAY_IN_LEDGERS: u32 = 17_280;
+
+/// Default policy: entries left with under 15 days are extended to 30
+pub const DEFAULT_THRESHOLD: u32 = 15 * DAY_IN_LEDGERS;
+pub const DEFAULT_EXTEND_TO: u32 = 30 * DAY_IN_LEDGERS;
+
+/// When entries are extended and for how long, in ledgers
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Policy {
+    pub threshold: u32,
+    pub extend_to: u32,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy { threshold: DEFAULT_THRESHOLD, extend_to: DEFAULT_EXTEND_TO }
+    }
+}
+
+impl Policy {
+    /// Whether the policy can be applied: a threshold below the extension, which the network allows
+    pub fn is_valid(&self, env: &Env) -> bool {
+        self.threshold > 0 && self.threshold < self.extend_to && self.extend_to <= env.storage().max_ttl()
+    }
+
+    /// Extend the contract instance if it is near archival
+    pub fn extend_instance(&self, env: &Env) {
+        env.storage().instance().extend_ttl(self.threshold, self.extend_to);
+    }
+
+    /// Extend a persistent entry if it exists and is near archival, returning whether it exists
+    pub fn extend<K: IntoVal<Env, Val>>(&self, env: &Env, key: &K) -> bool {
+        let persistent = env.storage().persistent();
+        if !persistent.has(key) {
+            return false;
+        }
+        persistent.extend_ttl(key, self.threshold, self.extend_to);
+        true
+    }
+
+    /// Persistent reads and writes that extend the entry, and the instance with it
+    pub fn has<K: IntoVal<Env, Val>>(&self, env: &Env, key: &K) -> bool {
+        self.extend_instance(env);
+        self.extend(env, key)
+    }
+
+    pub fn get<K: IntoVal<Env, Val>, V: TryFromVal<Env, Val>>(&self, env: &Env, key: &K) -> Option<V> {
+        self.extend_instance(env);
+        let value = env.storage().persistent().get(key);
+        if value.is_some() {
+            self.extend(env, key);
+        }
+        value
+    }
+
+    pub fn set<K: IntoVal<Env, Val>, V: IntoVal<Env, Val>>(&self, env: &Env, key: &K, value: &V) {
+        self.extend_instance(env);
+        env.storage().persistent().set(key, value);
+        self.extend(env, key);
+    }
+}